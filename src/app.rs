@@ -48,39 +48,79 @@
 * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
-use crate::{library::*, script::*, setting::*, system::*, window::*};
+use crate::{
+    argument::*, bookmark::*, instance::*, library::*, player::*, playlist::*, queue::*, script::*,
+    setting::*, statistic::*, system::*, window::*,
+};
 
 //================================================================
 
 use eframe::{CreationContext, egui};
-use std::{io::BufReader, time::Duration};
+use std::{
+    path::PathBuf,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
 //================================================================
 
 pub static mut GLOBAL_APP: *mut App = std::ptr::null_mut();
 
+/// alternate configuration folder, from the command-line.
+static CONFIGURATION_PATH: OnceLock<String> = OnceLock::new();
+
+/// running without a window, so every error is printed rather than shown in a dialog.
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+/// a termination or interrupt signal was caught, when running headless.
+static SIGNAL: AtomicBool = AtomicBool::new(false);
+
 pub struct App {
     pub library: Library,
     pub setting: Setting,
+    pub bookmark: Bookmark,
+    pub playlist: Playlist,
+    pub statistic: Statistic,
+    pub queue: Queue,
     pub window: Window,
     pub script: Script,
     pub system: System,
+    /// command-line argument, applied on the first frame.
+    pub argument: Option<Argument>,
 }
 
 impl App {
     pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+    /// maximum time between every step, when running headless.
+    const HEADLESS_TICK: Duration = Duration::from_secs(1);
 
-    pub fn new(context: &CreationContext) -> anyhow::Result<Self> {
+    /// Create the app. The window is none if running headless.
+    pub fn new(
+        context: &egui::Context,
+        window: Option<&CreationContext>,
+        argument: Argument,
+        instance: Instance,
+    ) -> anyhow::Result<Self> {
         let library = Library::new();
         let setting = Setting::new(context);
+        let system = System::new(&setting, context, window, instance)?;
+        let queue = Queue::new(system.player.get_sender());
         let window = Window::new(&library);
 
         Ok(Self {
             script: Script::new(&setting)?,
-            system: System::new(&setting, context)?,
+            system,
+            queue,
             window,
             library,
             setting,
+            bookmark: Bookmark::new(),
+            playlist: Playlist::new(),
+            statistic: Statistic::new(),
+            argument: Some(argument),
         })
     }
 
@@ -100,10 +140,10 @@ impl App {
     /// Get current play state.
     #[rustfmt::skip]
     pub fn get_play_state(&self) -> Option<(&Group, &Album, &Track)> {
-        if let Some((group, album, track)) = self.window.state {
-            let group = self.library.list_group.get(group).expect("get_play_state(): Invalid group window state.");
-            let album = group.list_album.get(album).expect("get_play_state(): Invalid album window state.");
-            let track = album.list_track.get(track).expect("get_play_state(): Invalid track window state.");
+        if let Some((group, album, track)) = self.queue.state {
+            let group = self.library.list_group.get(group).expect("get_play_state(): Invalid group play state.");
+            let album = group.list_album.get(album).expect("get_play_state(): Invalid album play state.");
+            let track = album.list_track.get(track).expect("get_play_state(): Invalid track play state.");
 
             return Some((group, album, track));
         }
@@ -116,38 +156,65 @@ impl App {
         track: (usize, usize, usize),
         context: &egui::Context,
     ) -> anyhow::Result<()> {
+        // set active play state.
+        self.queue.state = Some((track.0, track.1, track.2));
+        // get track data from play state.
+        let (_, _, track) = self.get_state(track);
+        let media = Media::new(track, self.bookmark.get(&self.setting, track));
+
+        self.system.player.play(&self.system.sink, &media)?;
+
+        self.track_begin(context)
+    }
+
+    /// Publish the current track once it has begun play-back, i.e. as a push notification and to the multi-media handler.
+    fn track_begin(&mut self, context: &egui::Context) -> anyhow::Result<()> {
         // TO-DO DO NOT DO THIS if the given track's cover is the same as the current track's cover. pointless.
         context.forget_all_images();
 
-        // set active window track state.
-        self.window.state = Some((track.0, track.1, track.2));
-        // get group, album, track data from window state.
+        // get group, album, track data from play state.
         let (group, album, track) = self
             .get_play_state()
-            .expect("track_add(): Invalid window state.");
-
-        let file = rodio::Decoder::new(BufReader::new(std::fs::File::open(&track.path)?))?;
+            .expect("track_begin(): Invalid play state.");
 
         // send push notification.
         self.system
             .push_notification(context, (group, album, track))?;
 
-        // kill the current track, add new track.
-        self.system.sink.stop();
-        self.system.sink.append(file);
+        let _ = self.system.set_metadata(Some((group, album, track)));
+        let _ = self.system.set_playback();
 
-        // append call-back for when the track is over.
-        let clone = context.clone();
-        self.system
-            .sink
-            .append(rodio::source::EmptyCallback::new(Box::new(move || {
-                clone.request_repaint();
-            })));
+        self.system.player.send(PlayerEvent::TrackStarted);
 
-        self.system.sink.play();
+        Ok(())
+    }
+
+    /// Remember the play position of the current track, if it's a long-form track.
+    #[rustfmt::skip]
+    pub fn track_mark(&mut self, time: Duration) {
+        if let Some((group, album, track)) = self.queue.state {
+            let group = self.library.list_group.get(group).expect("track_mark(): Invalid group play state.");
+            let album = group.list_album.get(album).expect("track_mark(): Invalid album play state.");
+            let track = album.list_track.get(track).expect("track_mark(): Invalid track play state.");
+
+            self.bookmark.set(&self.setting, track, time);
+        }
+    }
 
-        self.script
-            .call_all(Script::CALL_PLAY, self.system.sink.get_pos().as_secs());
+    /// Mark a track as finished, forgetting the last known play position. Skip to the next track if it is the current track.
+    pub fn track_finish(
+        &mut self,
+        index: (usize, usize, usize),
+        context: &egui::Context,
+    ) -> anyhow::Result<()> {
+        let (_, _, track) = self.get_state(index);
+        let key = track.get_key();
+
+        self.bookmark.finish(&key);
+
+        if self.queue.state == Some(index) {
+            self.track_skip_b(context)?;
+        }
 
         Ok(())
     }
@@ -164,6 +231,8 @@ impl App {
             self.script
                 .call_all(Script::CALL_PAUSE, self.system.sink.get_pos().as_secs());
         }
+
+        let _ = self.system.set_playback();
     }
 
     pub fn track_seek(&self, seek: i64, delta: bool) {
@@ -175,43 +244,104 @@ impl App {
             }
         };
 
+        // a seek past the start of the track is a seek to the start.
+        let seek = seek.max(0);
+
         let _ = self.system.sink.try_seek(Duration::from_secs(seek as u64));
+        let _ = self.system.set_playback();
 
         self.script.call_all(Script::CALL_SEEK, seek);
     }
 
     pub fn track_play(&self) {
         self.system.sink.play();
+        let _ = self.system.set_playback();
 
         self.script.call_all(Script::CALL_PLAY, ());
     }
 
     pub fn track_pause(&self) {
         self.system.sink.pause();
+        let _ = self.system.set_playback();
 
         self.script.call_all(Script::CALL_PAUSE, ());
     }
 
     pub fn track_set_volume(&self, volume: f32) {
         self.system.sink.set_volume(volume);
-
-        // TO-DO does there need to be a volume call-back?
+        let _ = self.system.set_volume();
     }
 
     pub fn track_stop(&mut self, call_script: bool) {
-        self.window.state = None;
-        self.system.sink.stop();
+        self.queue.state = None;
+        self.system.player.stop(&self.system.sink);
+
+        let _ = self.system.set_metadata(None);
+        let _ = self.system.set_playback();
 
         if call_script {
             self.script.call_all(Script::CALL_STOP, ());
         }
     }
 
+    /// Seek to the previous or next chapter of the current track. Returns false if there is no such chapter, or chapter skipping is disabled.
+    fn chapter_seek(&self, forward: bool) -> bool {
+        if !self.setting.chapter_skip {
+            return false;
+        }
+
+        if let Some((_, _, track)) = self.get_play_state() {
+            let time = self.system.sink.get_pos();
+
+            if let Some(index) = track.get_chapter(time) {
+                let index = {
+                    if forward {
+                        index + 1
+                    } else if time.saturating_sub(track.chapter[index].time)
+                        > Duration::from_secs(3)
+                    {
+                        // go back to the start of the current chapter first.
+                        index
+                    } else if index > 0 {
+                        index - 1
+                    } else {
+                        return false;
+                    }
+                };
+
+                if let Some(chapter) = track.chapter.get(index) {
+                    self.track_seek(chapter.time.as_secs() as i64, false);
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Skip to the previous chapter, or to the previous track if there is no previous chapter (or chapter skipping is disabled). Only for a skip button or media key.
+    pub fn chapter_skip_a(&mut self, context: &egui::Context) -> anyhow::Result<()> {
+        if self.chapter_seek(false) {
+            return Ok(());
+        }
+
+        self.track_skip_a(context)
+    }
+
+    /// Skip to the next chapter, or to the next track if there is no next chapter (or chapter skipping is disabled). Only for a skip button or media key.
+    pub fn chapter_skip_b(&mut self, context: &egui::Context) -> anyhow::Result<()> {
+        if self.chapter_seek(true) {
+            return Ok(());
+        }
+
+        self.track_skip_b(context)
+    }
+
     pub fn track_skip_a(&mut self, context: &egui::Context) -> anyhow::Result<()> {
-        if self.window.queue.1 > 0 {
-            if let Some(track) = self.window.queue.0.get(self.window.queue.1 - 1) {
-                self.window.queue.1 -= 1;
-                self.track_add(*track, context)?;
+        if let Some(index) = self.queue.back() {
+            if let Some(track) = self.queue.list.get(index).copied() {
+                self.queue.index = index;
+                self.track_add(track, context)?;
                 self.script.call_all(Script::CALL_SKIP_A, ());
             } else {
                 self.track_stop(false);
@@ -222,9 +352,7 @@ impl App {
     }
 
     pub fn track_skip_b(&mut self, context: &egui::Context) -> anyhow::Result<()> {
-        if let Some(track) = self.window.queue.0.get(self.window.queue.1 + 1) {
-            self.window.queue.1 += 1;
-            self.track_add(*track, context)?;
+        if self.track_next(context)? {
             self.script.call_all(Script::CALL_SKIP_B, ());
         } else {
             self.track_stop(false);
@@ -233,7 +361,83 @@ impl App {
         Ok(())
     }
 
+    /// Play the next queue entry. Returns false if there is no next queue entry.
+    pub fn track_next(&mut self, context: &egui::Context) -> anyhow::Result<bool> {
+        if let Some(index) = self.queue.next()
+            && let Some(track) = self.queue.list.get(index).copied()
+        {
+            self.queue.jump(index);
+            self.track_add(track, context)?;
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Follow the player thread once the current track was played to the end: play the next queue entry, unless told to stop. The player thread will usually have started the next entry already.
+    fn track_advance(
+        &mut self,
+        play: usize,
+        next: Option<(usize, (usize, usize, usize))>,
+        context: &egui::Context,
+    ) -> anyhow::Result<()> {
+        // a call-back from a track that was since replaced or stopped.
+        if !self.system.player.is_current(play) {
+            return Ok(());
+        }
+
+        if let Some(active) = self.queue.state {
+            // track was played to the end, forget the last known play position.
+            let (_, _, track) = self.get_state(active);
+            let key = track.get_key();
+            self.bookmark.finish(&key);
+            self.statistic.play(&key);
+            self.playlist.refresh();
+
+            // the player thread advances a copy of the queue the same way, so it will have started the same track, unless the queue has changed since.
+            match (self.queue.advance(), next) {
+                (Some(entry), Some((play, next))) if entry == next => {
+                    self.system.player.follow(play);
+                    self.track_begin(context)?;
+                }
+                (Some(entry), _) => self.track_add(entry, context)?,
+                (None, _) => self.track_stop(true),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hand the player thread a copy of the play order if it has changed, so that it can start every next track on its own (see Player).
+    fn track_plan(&mut self) {
+        let version = self.queue.get_version();
+
+        if self.queue.state.is_none() || self.system.player.is_planned(&version) {
+            return;
+        }
+
+        let list_media = self
+            .queue
+            .list
+            .iter()
+            .map(|entry| {
+                let (_, _, track) = self.get_state(*entry);
+                Media::new(track, self.bookmark.get(&self.setting, track))
+            })
+            .collect();
+
+        self.system
+            .player
+            .plan(version, self.queue.get_copy(), list_media);
+    }
+
     pub fn error(message: &str) {
+        if HEADLESS.load(Ordering::Relaxed) {
+            eprintln!("Error: {message}");
+            return;
+        }
+
         rfd::MessageDialog::new()
             .set_level(rfd::MessageLevel::Error)
             .set_title("Error")
@@ -243,22 +447,102 @@ impl App {
 
     pub fn error_result(result: anyhow::Result<()>) {
         if let Err(error) = result {
-            rfd::MessageDialog::new()
-                .set_level(rfd::MessageLevel::Error)
-                .set_title("Error")
-                .set_description(error.to_string())
-                .show();
+            Self::error(&error.to_string());
         }
     }
 
+    /// Get a path in the runtime folder, for a socket. Will use a private folder in the temporary folder if there is no runtime folder, and fail if that folder is not private to the user.
+    pub fn get_runtime_path(path: &str) -> anyhow::Result<PathBuf> {
+        if let Some(folder) = dirs::runtime_dir() {
+            return Ok(folder.join(path));
+        }
+
+        let user = std::env::var("USER").unwrap_or_default();
+        let folder = std::env::temp_dir().join(format!("melodix-{user}"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+            let error = |reason: &str| {
+                anyhow::anyhow!(
+                    "Couldn't use runtime folder \"{}\": {reason}",
+                    folder.display()
+                )
+            };
+
+            match std::fs::DirBuilder::new().mode(0o700).create(&folder) {
+                Err(error) if error.kind() != std::io::ErrorKind::AlreadyExists => {
+                    return Err(anyhow::anyhow!(
+                        "Couldn't create runtime folder \"{}\": {error}",
+                        folder.display()
+                    ));
+                }
+                _ => {}
+            }
+
+            // the temporary folder is shared, so another user may have made the folder first: only use it if it's really ours.
+            let data = std::fs::symlink_metadata(&folder).map_err(|x| error(&x.to_string()))?;
+
+            if !data.is_dir() {
+                return Err(error("not a folder."));
+            }
+
+            if data.uid() != unsafe { libc::getuid() } {
+                return Err(error("owned by another user."));
+            }
+
+            if data.mode() & 0o777 != 0o700 {
+                return Err(error("not private to the user."));
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = std::fs::create_dir_all(&folder);
+
+        Ok(folder.join(path))
+    }
+
+    /// Get a hash that is stable across every build (64-bit FNV-1a), unlike the standard library hasher, i.e. for a file name.
+    pub fn get_hash(data: &[u8]) -> u64 {
+        data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    /// Use an alternate configuration folder, rather than the default one. Must be set before any configuration path is used.
+    pub fn set_configuration_path(path: &str) -> anyhow::Result<()> {
+        std::fs::create_dir_all(path).map_err(|error| {
+            anyhow::anyhow!("Couldn't create configuration folder \"{path}\": {error}")
+        })?;
+
+        // use the same folder by any path to it, i.e. for the single-instance lock.
+        let path = std::fs::canonicalize(path)
+            .map(|x| x.display().to_string())
+            .unwrap_or(path.to_string());
+
+        let _ = CONFIGURATION_PATH.set(path);
+
+        Ok(())
+    }
+
+    /// Get the alternate configuration folder, if any.
+    pub fn get_configuration_folder() -> Option<String> {
+        CONFIGURATION_PATH.get().cloned()
+    }
+
     pub fn get_configuration_path(path: &str, folder: bool) -> String {
         let home = {
-            if let Some(path) = dirs::config_dir() {
-                let path = format!("{}/melodix/", path.display());
+            let path = match CONFIGURATION_PATH.get() {
+                Some(path) => Some(PathBuf::from(path)),
+                None => dirs::config_dir().map(|path| path.join("melodix")),
+            };
 
-                if let Ok(false) = std::fs::exists(&path) {
-                    std::fs::create_dir(&path).unwrap();
-                }
+            if let Some(path) = path {
+                let path = format!("{}/", path.display());
+
+                // any error will be reported on use.
+                let _ = std::fs::create_dir_all(&path);
 
                 path
             } else {
@@ -273,16 +557,58 @@ impl App {
             home
         };
 
-        if let Ok(false) = std::fs::exists(&exist) {
-            std::fs::create_dir(&exist).unwrap();
-        }
+        let _ = std::fs::create_dir_all(&exist);
 
         path
     }
-}
 
-impl eframe::App for App {
-    fn update(&mut self, context: &egui::Context, _: &mut eframe::Frame) {
+    /// Run the app without a window, until told to quit (or until a termination or interrupt signal). Every step will run as soon as there is an event, or at least once a second.
+    pub fn run_headless(argument: Argument, instance: Instance) -> anyhow::Result<()> {
+        HEADLESS.store(true, Ordering::Relaxed);
+
+        // quit the loop rather than the process on a signal, so that every drop (and socket removal) will still run.
+        #[cfg(unix)]
+        {
+            extern "C" fn on_signal(_: libc::c_int) {
+                SIGNAL.store(true, Ordering::Relaxed);
+            }
+
+            unsafe {
+                libc::signal(libc::SIGTERM, on_signal as *const () as libc::sighandler_t);
+                libc::signal(libc::SIGINT, on_signal as *const () as libc::sighandler_t);
+            }
+        }
+
+        // the context is never drawn, so use every repaint request as a wake-up call instead.
+        let context = egui::Context::default();
+        let (wake_tx, wake_rx) = std::sync::mpsc::channel();
+
+        context.set_request_repaint_callback(move |_| {
+            let _ = wake_tx.send(());
+        });
+
+        let mut app = Self::new(&context, None, argument, instance)?;
+
+        while !app.system.close {
+            let _ = wake_rx.recv_timeout(Self::HEADLESS_TICK);
+            while wake_rx.try_recv().is_ok() {}
+
+            // run an empty pass, or else the context will hold on to the last repaint request and never wake us up again.
+            let _ = context.run(egui::RawInput::default(), |_| {});
+
+            app.step(&context);
+            System::publish(&app);
+
+            if SIGNAL.load(Ordering::Relaxed) {
+                app.system.close = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run every non-UI task for this frame: script set-up, every pending system event, and every pending play-back event.
+    pub fn step(&mut self, context: &egui::Context) {
         if !self.script.initialize {
             unsafe {
                 GLOBAL_APP = self as *mut App;
@@ -292,14 +618,64 @@ impl eframe::App for App {
             self.script.initialize = true;
         }
 
-        if let Some(event) = self.system.poll_event() {
+        if let Some(argument) = self.argument.take() {
+            Self::error_result(argument.apply(self, context));
+        }
+
+        while let Some(event) = self.system.poll_event() {
             if let Err(error) = System::make_event(event, self, context) {
                 Self::error(&error.to_string());
             }
         }
 
+        self.handle_player(context);
+    }
+
+    /// Handle every pending play-back event, then hand the play order over to the player thread.
+    fn handle_player(&mut self, context: &egui::Context) {
+        let mut queue = false;
+
+        while let Some(event) = self.system.player.poll() {
+            match event {
+                PlayerEvent::TrackStarted => self
+                    .script
+                    .call_all(Script::CALL_PLAY, self.system.sink.get_pos().as_secs()),
+                PlayerEvent::TrackEnded(play, next) => {
+                    if let Err(error) = self.track_advance(play, next, context) {
+                        Self::error(&error.to_string());
+                    }
+                }
+                // a tick from a track that was since replaced, or that the app has yet to follow.
+                PlayerEvent::PositionTick(play, _) if !self.system.player.is_current(play) => {}
+                PlayerEvent::PositionTick(_, time) => {
+                    self.track_mark(time);
+                    self.script.call_all(Script::CALL_TICK, time.as_secs());
+                }
+                // a single queue modification may send more than one event.
+                PlayerEvent::QueueChanged => queue = true,
+            }
+        }
+
+        if queue {
+            self.queue.refresh();
+            self.script.call_all(Script::CALL_QUEUE, ());
+        }
+
+        self.track_plan();
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, context: &egui::Context, _: &mut eframe::Frame) {
+        self.step(context);
+
         if let Err(error) = Window::draw(self, context) {
             Self::error(&error.to_string());
         }
+
+        // handle every play-back event from drawing, i.e. a queue modification.
+        self.handle_player(context);
+
+        System::publish(self);
     }
 }
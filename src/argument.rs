@@ -0,0 +1,233 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{app::*, control::*, library::*, playlist::*, queue::*, system::*, window::*};
+
+//================================================================
+
+use clap::{Parser, Subcommand};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+//================================================================
+
+/// Melodix, a music player.
+#[derive(Parser, Default, Clone, Serialize, Deserialize)]
+#[command(version, about)]
+pub struct Argument {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// every file, folder or playlist file to play, replacing the queue.
+    pub path: Vec<String>,
+    /// add every file or folder to the end of the queue instead.
+    #[arg(short, long)]
+    pub enqueue: bool,
+    /// load a playlist file (M3U, PLS or XSPF) into the queue.
+    #[arg(short, long, value_name = "FILE")]
+    pub load: Option<String>,
+    /// start minimized to the tray.
+    #[arg(short, long)]
+    pub minimize: bool,
+    /// use an alternate configuration folder. also selects the instance to control with "melodix ctl".
+    #[arg(short, long, value_name = "FOLDER", global = true)]
+    pub configuration: Option<String>,
+    /// re-scan the library folder on start-up. will scan the given folder instead, if any.
+    #[arg(short, long, value_name = "FOLDER")]
+    pub scan: Option<Option<String>>,
+    /// print a desktop entry, with every supported file type, and exit.
+    #[arg(long)]
+    pub desktop: bool,
+    /// run without a window. control with "melodix ctl", the remote API or an MPD client.
+    #[arg(long)]
+    pub headless: bool,
+}
+
+#[derive(Subcommand, Clone, Serialize, Deserialize)]
+pub enum Command {
+    /// send a control request to the running instance.
+    Ctl {
+        #[command(subcommand)]
+        request: Request,
+        /// print the response as JSON.
+        #[arg(long, global = true)]
+        json: bool,
+    },
+}
+
+impl Argument {
+    /// Apply every command-line argument to the app.
+    pub fn apply(self, app: &mut App, context: &egui::Context) -> anyhow::Result<()> {
+        if self.minimize {
+            if app.setting.window_tray {
+                System::toggle_visible(app, context);
+            } else {
+                // without a tray icon, a hidden window couldn't be shown again.
+                context.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            }
+        }
+
+        if let Some(path) = self.scan {
+            let Some(path) = path.or(app.setting.library_path.clone()) else {
+                return Err(anyhow::anyhow!("No library folder to re-scan."));
+            };
+
+            Window::library_scan(app, PathBuf::from(path));
+        }
+
+        if let Some(path) = self.load {
+            Window::queue_load(app, context, PathBuf::from(path))?;
+        }
+
+        let mut list = Vec::new();
+        let mut error = Vec::new();
+
+        if self
+            .path
+            .iter()
+            .any(|x| Playlist::is_playlist(Path::new(x)))
+        {
+            app.window.unresolved.clear();
+        }
+
+        // skip every path that can't be read, rather than the whole argument list.
+        for path in &self.path {
+            if !std::fs::exists(path).unwrap_or_default() {
+                error.push(format!("Couldn't find file or folder \"{path}\"."));
+                continue;
+            }
+
+            if Playlist::is_playlist(Path::new(path)) {
+                match Window::queue_read(app, Path::new(path)) {
+                    Ok(entry) => list.extend(entry),
+                    Err(reason) => {
+                        error.push(format!("Couldn't read playlist \"{path}\": {reason}"))
+                    }
+                }
+            } else {
+                list.extend(app.library.insert(path));
+            }
+        }
+
+        if !list.is_empty() {
+            if self.enqueue {
+                Queue::add(app, list, false, context)?;
+            } else {
+                Queue::play(app, list, 0, context)?;
+            }
+        }
+
+        if !error.is_empty() {
+            return Err(anyhow::anyhow!(error.join("\n")));
+        }
+
+        Ok(())
+    }
+
+    /// Get the argument to forward to a running instance: every file or folder to play, as an absolute path.
+    pub fn get_forward(&self) -> Self {
+        let absolute = |path: &String| {
+            std::path::absolute(path)
+                .map(|x| x.display().to_string())
+                .unwrap_or(path.clone())
+        };
+
+        Self {
+            path: self.path.iter().map(absolute).collect(),
+            enqueue: self.enqueue,
+            load: self.load.as_ref().map(absolute),
+            ..Default::default()
+        }
+    }
+
+    /// Check if there is anything to play.
+    pub fn is_empty(&self) -> bool {
+        self.path.is_empty() && self.load.is_none()
+    }
+
+    /// Make a desktop entry, with a MIME type for every supported file type.
+    pub fn make_desktop() -> String {
+        let mut list_type: Vec<&str> = Vec::new();
+
+        for extension in Track::FORMAT_LIST {
+            list_type.extend(Self::get_type(extension));
+        }
+
+        list_type.extend(Self::get_type("cue"));
+        list_type.extend(["audio/x-mpegurl", "audio/x-scpls", "application/xspf+xml"]);
+
+        format!(
+            "[Desktop Entry]
+Type=Application
+Name=Melodix
+Comment=Music player
+Exec=melodix %F
+Icon=melodix
+Terminal=false
+Categories=AudioVideo;Audio;Player;
+MimeType={};
+",
+            list_type.join(";")
+        )
+    }
+
+    /// Get every MIME type for a file extension.
+    fn get_type(extension: &str) -> &'static [&'static str] {
+        match extension {
+            "mp3" => &["audio/mpeg", "audio/mp3"],
+            "flac" => &["audio/flac", "audio/x-flac"],
+            "wav" => &["audio/wav", "audio/x-wav"],
+            "m4a" => &["audio/mp4", "audio/x-m4a"],
+            "m4b" => &["audio/x-m4b"],
+            "cue" => &["application/x-cue"],
+            _ => &[],
+        }
+    }
+}
@@ -0,0 +1,122 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{library::*, setting::*, store::*};
+
+//================================================================
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+//================================================================
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// last known play position, keyed by the track key (see Track::get_key).
+    pub list_mark: HashMap<String, Duration>,
+}
+
+impl Bookmark {
+    /// genre list that will always be considered long-form, regardless of track length.
+    const GENRE_LIST: [&str; 4] = ["Audiobook", "Audio Theatre", "Podcast", "Speech"];
+    /// do not resume a track if the bookmark is this close to the start or the end of it.
+    const TIME_MARGIN: Duration = Duration::from_secs(5);
+
+    pub fn new() -> Self {
+        Self::load()
+    }
+
+    /// Check if a track should have its play position remembered.
+    pub fn is_long_form(setting: &Setting, track: &Track) -> bool {
+        if setting.bookmark_kind && Self::GENRE_LIST.iter().any(|kind| track.has_genre(kind)) {
+            return true;
+        }
+
+        setting.bookmark_time > 0 && track.time.as_secs() >= setting.bookmark_time * 60
+    }
+
+    /// Get the play position to resume a track from, if any.
+    pub fn get(&self, setting: &Setting, track: &Track) -> Option<Duration> {
+        if !Self::is_long_form(setting, track) {
+            return None;
+        }
+
+        let time = self.list_mark.get(&track.get_key())?;
+
+        if *time < Self::TIME_MARGIN || *time + Self::TIME_MARGIN >= track.time {
+            return None;
+        }
+
+        Some(*time)
+    }
+
+    /// Remember the play position for a track.
+    pub fn set(&mut self, setting: &Setting, track: &Track, time: Duration) {
+        if Self::is_long_form(setting, track) {
+            self.list_mark.insert(track.get_key(), time);
+        }
+    }
+
+    /// Forget the play position for a track, i.e. mark it as finished.
+    pub fn finish(&mut self, key: &str) {
+        self.list_mark.remove(key);
+    }
+}
+
+impl Store for Bookmark {
+    const PATH: &'static str = "bookmark.data";
+    const VERSION: u32 = 1;
+}
+
+impl Drop for Bookmark {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
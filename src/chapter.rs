@@ -0,0 +1,538 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+use symphonia::core::{formats::Cue, meta::Tag};
+
+//================================================================
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    pub name: String,
+    pub time: Duration,
+}
+
+impl Chapter {
+    /// FLAC CD-DA lead-out track index.
+    const CUE_LEAD_OUT: u32 = 170;
+
+    /// Scan every chapter in a track. Vorbis CHAPTER tags take priority, then FLAC cue sheets, then ID3 CHAP frames and MP4 Nero chapters.
+    pub fn scan(path: &Path, tag: &[Tag], cue: &[Cue], rate: Option<u32>) -> Vec<Self> {
+        let mut list = Self::from_tag(tag);
+
+        if list.is_empty()
+            && let Some(rate) = rate
+        {
+            list = Self::from_cue(cue, rate);
+        }
+
+        if list.is_empty()
+            && let Some(extension) = path.extension()
+        {
+            let result = {
+                if extension == "mp3" {
+                    Self::from_id3(path)
+                } else if extension == "m4a" || extension == "m4b" || extension == "mp4" {
+                    Self::from_mp4(path)
+                } else {
+                    Ok(Vec::default())
+                }
+            };
+
+            list = result.unwrap_or_default();
+        }
+
+        list.sort_by_key(|x| x.time);
+
+        // a single chapter is no different than a flat track.
+        if list.len() < 2 {
+            list.clear();
+        }
+
+        list
+    }
+
+    /// Parse a "HH:MM:SS.mmm" Vorbis chapter time-stamp. Returns none for a negative, infinite or NaN time-stamp.
+    fn parse_time(text: &str) -> Option<Duration> {
+        let mut time = 0.0;
+
+        for part in text.trim().split(':') {
+            time = time * 60.0 + part.parse::<f64>().ok()?;
+        }
+
+        Duration::try_from_secs_f64(time).ok()
+    }
+
+    // https://wiki.xiph.org/Chapter_Extension
+    fn from_tag(tag: &[Tag]) -> Vec<Self> {
+        let mut list = Vec::new();
+
+        for entry in tag {
+            let key = entry.key.to_uppercase();
+
+            // only look for the time-stamp key, i.e. CHAPTER001, and not CHAPTER001NAME.
+            if let Some(index) = key.strip_prefix("CHAPTER")
+                && !index.is_empty()
+                && index.chars().all(|x| x.is_ascii_digit())
+                && let Some(time) = Self::parse_time(&entry.value.to_string())
+            {
+                let name = tag
+                    .iter()
+                    .find(|x| x.key.to_uppercase() == format!("{key}NAME"))
+                    .map(|x| x.value.to_string())
+                    .unwrap_or_else(|| format!("Chapter {}", list.len() + 1));
+
+                list.push(Self { name, time });
+            }
+        }
+
+        list
+    }
+
+    fn from_cue(cue: &[Cue], rate: u32) -> Vec<Self> {
+        cue.iter()
+            .filter(|x| x.index != Self::CUE_LEAD_OUT)
+            .filter_map(|x| {
+                let name = x
+                    .tags
+                    .iter()
+                    .find(|x| x.std_key == Some(symphonia::core::meta::StandardTagKey::TrackTitle))
+                    .map(|x| x.value.to_string())
+                    .unwrap_or_else(|| format!("Chapter {}", x.index));
+
+                // a zero sample rate would give an infinite time.
+                let time = Duration::try_from_secs_f64(x.start_ts as f64 / rate as f64).ok()?;
+
+                Some(Self { name, time })
+            })
+            .collect()
+    }
+
+    //================================================================
+
+    fn read_u32(data: &[u8]) -> u32 {
+        u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+    }
+
+    fn read_safe(data: &[u8]) -> u32 {
+        (data[0] as u32 & 0x7f) << 21
+            | (data[1] as u32 & 0x7f) << 14
+            | (data[2] as u32 & 0x7f) << 7
+            | (data[3] as u32 & 0x7f)
+    }
+
+    fn read_text(data: &[u8]) -> String {
+        let Some((kind, data)) = data.split_first() else {
+            return String::default();
+        };
+
+        let text = match kind {
+            // ISO-8859-1.
+            0 => data.iter().map(|x| *x as char).collect(),
+            // UTF-16, with or without BOM.
+            1 | 2 => {
+                let (data, little) = match data {
+                    [0xff, 0xfe, rest @ ..] => (rest, true),
+                    [0xfe, 0xff, rest @ ..] => (rest, false),
+                    _ => (data, false),
+                };
+
+                let data: Vec<u16> = data
+                    .chunks_exact(2)
+                    .map(|x| {
+                        if little {
+                            u16::from_le_bytes([x[0], x[1]])
+                        } else {
+                            u16::from_be_bytes([x[0], x[1]])
+                        }
+                    })
+                    .collect();
+
+                String::from_utf16_lossy(&data)
+            }
+            // UTF-8.
+            _ => String::from_utf8_lossy(data).to_string(),
+        };
+
+        text.trim_end_matches('\0').to_string()
+    }
+
+    // https://id3.org/id3v2-chapters-1.0
+    fn from_id3(path: &Path) -> anyhow::Result<Vec<Self>> {
+        Self::read_id3(&mut BufReader::new(File::open(path)?))
+    }
+
+    fn read_id3<R: Read>(file: &mut R) -> anyhow::Result<Vec<Self>> {
+        let mut head = [0u8; 10];
+
+        file.read_exact(&mut head)?;
+
+        if &head[0..3] != b"ID3" {
+            return Ok(Vec::default());
+        }
+
+        let version = head[3];
+        let mut data = vec![0u8; Self::read_safe(&head[6..10]) as usize];
+
+        file.read_exact(&mut data)?;
+
+        let mut cursor = 0;
+
+        // skip extended header.
+        if head[5] & 0x40 != 0 && data.len() >= 4 {
+            cursor = if version >= 4 {
+                Self::read_safe(&data[0..4]) as usize
+            } else {
+                Self::read_u32(&data[0..4]) as usize + 4
+            };
+        }
+
+        let mut list = Vec::new();
+
+        for (name, body) in Self::read_frame(&data[cursor.min(data.len())..], version) {
+            if &name != b"CHAP" {
+                continue;
+            }
+
+            // element ID, null-terminated.
+            let Some(split) = body.iter().position(|x| *x == 0) else {
+                continue;
+            };
+            let body = &body[split + 1..];
+
+            // start time, end time, start offset, end offset.
+            if body.len() < 16 {
+                continue;
+            }
+
+            let time = Duration::from_millis(Self::read_u32(&body[0..4]) as u64);
+
+            let name = Self::read_frame(&body[16..], version)
+                .into_iter()
+                .find(|(name, _)| name == b"TIT2")
+                .map(|(_, body)| Self::read_text(body))
+                .unwrap_or_else(|| format!("Chapter {}", list.len() + 1));
+
+            list.push(Self { name, time });
+        }
+
+        Ok(list)
+    }
+
+    fn read_frame(mut data: &[u8], version: u8) -> Vec<([u8; 4], &[u8])> {
+        let mut list = Vec::new();
+
+        while data.len() >= 10 && data[0] != 0 {
+            let name = [data[0], data[1], data[2], data[3]];
+            let size = if version >= 4 {
+                Self::read_safe(&data[4..8])
+            } else {
+                Self::read_u32(&data[4..8])
+            } as usize;
+
+            if 10 + size > data.len() {
+                break;
+            }
+
+            list.push((name, &data[10..10 + size]));
+            data = &data[10 + size..];
+        }
+
+        list
+    }
+
+    //================================================================
+
+    /// Find an atom by name in between the current position and the end position, and return the end position of that atom.
+    fn find_atom<R: Read + Seek>(
+        file: &mut R,
+        name: &[u8; 4],
+        end: u64,
+    ) -> anyhow::Result<Option<u64>> {
+        let mut head = [0u8; 8];
+
+        while file.stream_position()? + 8 <= end {
+            let start = file.stream_position()?;
+
+            file.read_exact(&mut head)?;
+
+            let size = match Self::read_u32(&head[0..4]) {
+                // atom extends to the end of the parent.
+                0 => end - start,
+                // atom has a 64-bit size.
+                1 => {
+                    let mut large = [0u8; 8];
+                    file.read_exact(&mut large)?;
+                    u64::from_be_bytes(large)
+                }
+                size => size as u64,
+            };
+
+            if size < 8 {
+                break;
+            }
+
+            if &head[4..8] == name {
+                return Ok(Some(start + size));
+            }
+
+            file.seek(SeekFrom::Start(start + size))?;
+        }
+
+        Ok(None)
+    }
+
+    // Nero chapter list, i.e. moov/udta/chpl.
+    fn from_mp4(path: &Path) -> anyhow::Result<Vec<Self>> {
+        Self::read_mp4(&mut BufReader::new(File::open(path)?))
+    }
+
+    fn read_mp4<R: Read + Seek>(file: &mut R) -> anyhow::Result<Vec<Self>> {
+        // Nero chapter time-stamps are in 100 nano-second units.
+        const TIME_SCALE: u64 = 10_000_000;
+
+        let end = file.seek(SeekFrom::End(0))?;
+
+        file.seek(SeekFrom::Start(0))?;
+
+        let mut end = Some(end);
+
+        for name in [b"moov", b"udta", b"chpl"] {
+            end = match end {
+                Some(end) => Self::find_atom(file, name, end)?,
+                None => return Ok(Vec::default()),
+            };
+        }
+
+        if end.is_none() {
+            return Ok(Vec::default());
+        }
+
+        let mut head = [0u8; 4];
+        file.read_exact(&mut head)?;
+
+        // version 1 has an additional reserved field.
+        if head[0] != 0 {
+            file.seek(SeekFrom::Current(4))?;
+        }
+
+        let mut size = [0u8; 1];
+        file.read_exact(&mut size)?;
+
+        let mut list = Vec::new();
+
+        for _ in 0..size[0] {
+            let mut time = [0u8; 8];
+            let mut size = [0u8; 1];
+
+            file.read_exact(&mut time)?;
+            file.read_exact(&mut size)?;
+
+            let mut name = vec![0u8; size[0] as usize];
+            file.read_exact(&mut name)?;
+
+            // skip a chapter with a time-stamp too large to be real, from a malformed atom.
+            let Some(time) = u64::from_be_bytes(time).checked_mul(1_000_000_000 / TIME_SCALE)
+            else {
+                continue;
+            };
+
+            list.push(Self {
+                name: String::from_utf8_lossy(&name).to_string(),
+                time: Duration::from_nanos(time),
+            });
+        }
+
+        Ok(list)
+    }
+}
+
+//================================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Make an ID3v2.4 frame, with a sync-safe size.
+    fn make_frame(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let size = body.len() as u32;
+        let mut data = name.to_vec();
+
+        data.extend([
+            (size >> 21) as u8 & 0x7f,
+            (size >> 14) as u8 & 0x7f,
+            (size >> 7) as u8 & 0x7f,
+            size as u8 & 0x7f,
+        ]);
+        data.extend([0, 0]);
+        data.extend(body);
+        data
+    }
+
+    fn make_chap(id: &str, time: u32, name: &str) -> Vec<u8> {
+        let mut body = id.as_bytes().to_vec();
+        body.push(0);
+        body.extend(time.to_be_bytes());
+        body.extend((time + 1000).to_be_bytes());
+        body.extend([0xff; 8]);
+
+        let mut text = vec![3];
+        text.extend(name.as_bytes());
+        body.extend(make_frame(b"TIT2", &text));
+
+        make_frame(b"CHAP", &body)
+    }
+
+    fn make_atom(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend(name);
+        data.extend(body);
+        data
+    }
+
+    #[test]
+    fn parse_time() {
+        assert_eq!(
+            Chapter::parse_time("00:01:30.500"),
+            Some(Duration::from_millis(90_500))
+        );
+        assert_eq!(Chapter::parse_time("90"), Some(Duration::from_secs(90)));
+        assert_eq!(Chapter::parse_time("-1"), None);
+        assert_eq!(Chapter::parse_time("inf"), None);
+        assert_eq!(Chapter::parse_time("NaN"), None);
+        assert_eq!(Chapter::parse_time("a:b"), None);
+    }
+
+    #[test]
+    fn read_id3() {
+        let mut body = make_chap("ch0", 0, "Intro");
+        body.extend(make_chap("ch1", 61_000, "Second"));
+        body.extend([0; 16]);
+
+        let size = body.len() as u32;
+        let mut data = b"ID3".to_vec();
+        data.extend([4, 0, 0]);
+        data.extend([
+            (size >> 21) as u8 & 0x7f,
+            (size >> 14) as u8 & 0x7f,
+            (size >> 7) as u8 & 0x7f,
+            size as u8 & 0x7f,
+        ]);
+        data.extend(body);
+
+        let list = Chapter::read_id3(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].name, "Intro");
+        assert_eq!(list[1].name, "Second");
+        assert_eq!(list[1].time, Duration::from_millis(61_000));
+    }
+
+    #[test]
+    fn read_id3_without_tag() {
+        let list = Chapter::read_id3(&mut Cursor::new(vec![0xff; 32])).unwrap();
+
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn read_mp4() {
+        let mut chpl = vec![0, 0, 0, 0, 2];
+
+        for (time, name) in [(0u64, "Intro"), (15_000_000_000, "Second")] {
+            chpl.extend(time.to_be_bytes());
+            chpl.push(name.len() as u8);
+            chpl.extend(name.as_bytes());
+        }
+
+        let udta = make_atom(b"udta", &make_atom(b"chpl", &chpl));
+        let mut data = make_atom(b"ftyp", b"M4A ");
+        data.extend(make_atom(b"moov", &udta));
+
+        let list = Chapter::read_mp4(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].name, "Intro");
+        assert_eq!(list[1].time, Duration::from_secs(1500));
+    }
+
+    #[test]
+    fn read_mp4_overflow() {
+        let mut chpl = vec![0, 0, 0, 0, 2];
+
+        for (time, name) in [(u64::MAX, "Broken"), (10_000_000, "Valid")] {
+            chpl.extend(time.to_be_bytes());
+            chpl.push(name.len() as u8);
+            chpl.extend(name.as_bytes());
+        }
+
+        let data = make_atom(b"moov", &make_atom(b"udta", &make_atom(b"chpl", &chpl)));
+        let list = Chapter::read_mp4(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, "Valid");
+        assert_eq!(list[0].time, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn read_mp4_without_chapter() {
+        let data = make_atom(b"moov", &make_atom(b"trak", &[0; 8]));
+        let list = Chapter::read_mp4(&mut Cursor::new(data)).unwrap();
+
+        assert!(list.is_empty());
+    }
+}
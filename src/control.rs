@@ -0,0 +1,334 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{app::*, instance::*, window::*};
+
+//================================================================
+
+use clap::Subcommand;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{Receiver, Sender};
+
+#[cfg(unix)]
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    time::Duration,
+};
+
+//================================================================
+
+/// control request, with a sender for the response.
+pub type Message = (Request, Sender<Response>);
+
+/// control request, from a control socket client.
+#[derive(Subcommand, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Request {
+    /// resume play-back.
+    Play,
+    /// pause play-back.
+    Pause,
+    /// toggle play-back.
+    Toggle,
+    /// skip to the next track.
+    Next,
+    /// skip to the previous track.
+    Previous,
+    /// stop play-back.
+    Stop,
+    /// seek to a time into the current track, in seconds.
+    Seek { time: u64 },
+    /// set the volume, from 0.0 to 1.0.
+    Volume { volume: f32 },
+    /// add every file or folder to the end of the queue.
+    Enqueue {
+        #[arg(required = true)]
+        path: Vec<String>,
+    },
+    /// get the current play state.
+    Status,
+    /// quit the running instance.
+    Quit,
+}
+
+/// control response, with the current play state.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Response {
+    /// error message, if the request failed.
+    pub error: Option<String>,
+    /// play-back status: "play", "pause" or "stop".
+    pub status: String,
+    pub group: Option<String>,
+    pub album: Option<String>,
+    pub track: Option<String>,
+    pub path: Option<String>,
+    /// play position, in seconds.
+    pub time: u64,
+    /// track length, in seconds.
+    pub length: u64,
+    pub volume: f32,
+}
+
+impl Response {
+    pub fn new(app: &App, error: Option<String>) -> Self {
+        let state = app.get_play_state();
+
+        let status = if state.is_none() || app.system.sink.empty() {
+            "stop"
+        } else if app.system.sink.is_paused() {
+            "pause"
+        } else {
+            "play"
+        };
+
+        Self {
+            error,
+            status: status.to_string(),
+            group: state.map(|(group, _, _)| group.name.clone()),
+            album: state.map(|(_, album, _)| album.name.clone()),
+            track: state.map(|(_, _, track)| track.name.clone()),
+            path: state.map(|(_, _, track)| track.path.clone()),
+            time: app.system.sink.get_pos().as_secs(),
+            length: state
+                .map(|(_, _, track)| track.time.as_secs())
+                .unwrap_or_default(),
+            volume: app.system.sink.volume(),
+        }
+    }
+
+    /// Get the response as a line of human-readable text.
+    pub fn get_text(&self) -> String {
+        match (&self.group, &self.album, &self.track) {
+            (Some(group), Some(album), Some(track)) => format!(
+                "{}: {group} - {album} - {track} ({}/{})",
+                self.status,
+                Window::format_time(self.time as usize),
+                Window::format_time(self.length as usize)
+            ),
+            _ => self.status.clone(),
+        }
+    }
+}
+
+//================================================================
+
+/// Local control server, as a Unix domain socket in the runtime folder. Every request is a line of JSON, and is answered with a line of JSON.
+pub struct Control {
+    /// socket path.
+    #[cfg(unix)]
+    path: PathBuf,
+}
+
+impl Control {
+    #[cfg(unix)]
+    const PATH_SOCKET: &str = "melodix.control";
+    /// maximum time to wait for the app to answer a request.
+    #[cfg(unix)]
+    const TIME_OUT: Duration = Duration::from_secs(5);
+
+    /// Listen for every control request, on a separate thread. Every request comes with a sender for the response. Returns none without the single-instance lock.
+    #[cfg(unix)]
+    pub fn new(
+        context: egui::Context,
+        instance: &Instance,
+    ) -> anyhow::Result<Option<(Self, Receiver<Message>)>> {
+        // without the lock, a socket left behind can't be told apart from the socket of a running instance.
+        if !instance.is_locked() {
+            return Ok(None);
+        }
+
+        let path = Instance::get_path(Self::PATH_SOCKET)?;
+
+        // only the lock holder can own the socket, so any socket left behind is stale.
+        let _ = std::fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path).map_err(|error| {
+            anyhow::anyhow!(
+                "Couldn't create control socket \"{}\": {error}",
+                path.display()
+            )
+        })?;
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        // only the user can connect, rather than every user by the default permission.
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                let context = context.clone();
+
+                std::thread::spawn(move || Self::handle(stream, tx, context));
+            }
+        });
+
+        Ok(Some((Self { path }, rx)))
+    }
+
+    #[cfg(not(unix))]
+    pub fn new(
+        _: egui::Context,
+        _: &Instance,
+    ) -> anyhow::Result<Option<(Self, Receiver<Message>)>> {
+        Ok(None)
+    }
+
+    /// Answer every request from a control client, until the client disconnects.
+    #[cfg(unix)]
+    fn handle(stream: UnixStream, tx: Sender<Message>, context: egui::Context) {
+        let Ok(reader) = stream.try_clone() else {
+            return;
+        };
+        let mut writer = stream;
+
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => {
+                    let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+                    if tx.send((request, response_tx)).is_err() {
+                        return;
+                    }
+
+                    context.request_repaint();
+
+                    response_rx
+                        .recv_timeout(Self::TIME_OUT)
+                        .unwrap_or_else(|_| Response {
+                            error: Some("Timed out waiting for a response.".to_string()),
+                            ..Default::default()
+                        })
+                }
+                Err(error) => Response {
+                    error: Some(error.to_string()),
+                    ..Default::default()
+                },
+            };
+
+            if let Ok(response) = serde_json::to_string(&response)
+                && writeln!(writer, "{response}").is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Send a request to the running instance, and print the response. Returns the process exit code.
+    pub fn run(request: Request, json: bool) -> i32 {
+        match Self::send(request) {
+            Ok(response) => {
+                if json {
+                    println!("{}", serde_json::to_string(&response).unwrap_or_default());
+                } else {
+                    println!("{}", response.get_text());
+                }
+
+                if let Some(error) = response.error {
+                    eprintln!("{error}");
+                    return 1;
+                }
+
+                0
+            }
+            Err(error) => {
+                eprintln!("{error}");
+                1
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn send(request: Request) -> anyhow::Result<Response> {
+        // the running instance may have a different working folder.
+        let request = match request {
+            Request::Enqueue { path } => Request::Enqueue {
+                path: path
+                    .into_iter()
+                    .map(|x| {
+                        std::path::absolute(&x)
+                            .map(|x| x.display().to_string())
+                            .unwrap_or(x)
+                    })
+                    .collect(),
+            },
+            request => request,
+        };
+
+        let mut stream = UnixStream::connect(Instance::get_path(Self::PATH_SOCKET)?)
+            .map_err(|_| anyhow::anyhow!("Couldn't connect to Melodix. Is it running?"))?;
+
+        writeln!(stream, "{}", serde_json::to_string(&request)?)?;
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    #[cfg(not(unix))]
+    fn send(_: Request) -> anyhow::Result<Response> {
+        Err(anyhow::anyhow!(
+            "The control socket is only available on Unix."
+        ))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Control {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,251 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use rodio::{Source, source::SeekError};
+use std::{path::Path, time::Duration};
+
+//================================================================
+
+/// A parsed CUE sheet.
+#[derive(Default, Debug)]
+pub struct CueSheet {
+    pub group: Option<String>,
+    pub album: Option<String>,
+    pub date: Option<String>,
+    pub kind: Option<String>,
+    pub list_track: Vec<CueTrack>,
+}
+
+/// A single track entry in a CUE sheet.
+#[derive(Default, Debug)]
+pub struct CueTrack {
+    /// absolute path to the audio file this track is a part of.
+    pub path: String,
+    pub name: Option<String>,
+    pub group: Option<String>,
+    pub track: Option<usize>,
+    /// start time of the track (INDEX 01) in the audio file.
+    pub time: Duration,
+}
+
+impl CueSheet {
+    /// CD-DA frame count per second.
+    const FRAME_RATE: u64 = 75;
+
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path)?;
+        let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&data);
+
+        // not every CUE sheet is in UTF-8, fall back to ISO-8859-1.
+        let text = match std::str::from_utf8(data) {
+            Ok(text) => text.to_string(),
+            Err(_) => data.iter().map(|x| *x as char).collect(),
+        };
+
+        let folder = path.parent().unwrap_or(Path::new(""));
+        let mut sheet = Self::default();
+        let mut file = None;
+
+        for line in text.lines() {
+            let line = line.trim();
+            let (command, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+
+            match command.to_uppercase().as_str() {
+                "REM" => {
+                    let (command, value) =
+                        value.split_once(char::is_whitespace).unwrap_or((value, ""));
+                    let value = Some(Self::unquote(value));
+
+                    match command.to_uppercase().as_str() {
+                        "DATE" => sheet.date = value,
+                        "GENRE" => sheet.kind = value,
+                        _ => {}
+                    }
+                }
+                "FILE" => {
+                    // the file type is the last word, i.e. WAVE, MP3, etc.
+                    let name = value
+                        .rsplit_once(char::is_whitespace)
+                        .map_or(value, |(name, _)| name);
+                    let name = folder.join(Self::unquote(name));
+
+                    file = Some(name.display().to_string());
+                }
+                "TRACK" => {
+                    if let Some(path) = &file {
+                        sheet.list_track.push(CueTrack {
+                            path: path.clone(),
+                            track: value.split_whitespace().next().and_then(|x| x.parse().ok()),
+                            ..Default::default()
+                        });
+                    }
+                }
+                "TITLE" => match sheet.list_track.last_mut() {
+                    Some(track) => track.name = Some(Self::unquote(value)),
+                    None => sheet.album = Some(Self::unquote(value)),
+                },
+                "PERFORMER" => match sheet.list_track.last_mut() {
+                    Some(track) => track.group = Some(Self::unquote(value)),
+                    None => sheet.group = Some(Self::unquote(value)),
+                },
+                "INDEX" => {
+                    if let Some(track) = sheet.list_track.last_mut()
+                        && let Some((index, time)) = value.split_once(char::is_whitespace)
+                        && index.parse::<usize>() == Ok(1)
+                        && let Some(time) = Self::parse_time(time.trim())
+                    {
+                        track.time = time;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    fn unquote(text: &str) -> String {
+        text.trim().trim_matches('"').to_string()
+    }
+
+    /// Parse a "MM:SS:FF" time-stamp.
+    fn parse_time(text: &str) -> Option<Duration> {
+        let mut part = text.split(':').map(|x| x.parse::<u64>());
+
+        let (Some(Ok(minute)), Some(Ok(second)), Some(Ok(frame)), None) =
+            (part.next(), part.next(), part.next(), part.next())
+        else {
+            return None;
+        };
+
+        let frame = (minute * 60 + second) * Self::FRAME_RATE + frame;
+
+        Some(Duration::from_millis(frame * 1000 / Self::FRAME_RATE))
+    }
+}
+
+//================================================================
+
+/// A part of an audio source, used for play-back of a CUE sheet track. Seeking is relative to the start of the part.
+pub struct CueSource<S: Source> {
+    source: S,
+    start: Duration,
+    time: Duration,
+    /// sample count left before the end of the part.
+    count: u64,
+}
+
+impl<S: Source> CueSource<S> {
+    pub fn new(mut source: S, start: Duration, time: Duration) -> anyhow::Result<Self> {
+        source
+            .try_seek(start)
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+
+        let count = Self::get_count(&source, time);
+
+        Ok(Self {
+            source,
+            start,
+            time,
+            count,
+        })
+    }
+
+    fn get_count(source: &S, time: Duration) -> u64 {
+        (time.as_secs_f64() * source.sample_rate() as f64 * source.channels() as f64) as u64
+    }
+}
+
+impl<S: Source> Iterator for CueSource<S> {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count == 0 {
+            return None;
+        }
+
+        self.count -= 1;
+        self.source.next()
+    }
+}
+
+impl<S: Source> Source for CueSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        let count = self.count.try_into().unwrap_or(usize::MAX);
+
+        Some(
+            self.source
+                .current_span_len()
+                .map_or(count, |x| x.min(count)),
+        )
+    }
+
+    fn channels(&self) -> rodio::ChannelCount {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> rodio::SampleRate {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(self.time)
+    }
+
+    fn try_seek(&mut self, time: Duration) -> Result<(), SeekError> {
+        let time = time.min(self.time);
+
+        self.source.try_seek(self.start + time)?;
+        self.count = Self::get_count(&self.source, self.time - time);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{app::*, argument::*};
+
+//================================================================
+
+use eframe::egui;
+use std::sync::mpsc::Receiver;
+
+#[cfg(unix)]
+use std::{
+    fs::{File, TryLockError},
+    io::{Read, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+//================================================================
+
+/// Single-instance lock, as a Unix domain socket in the runtime folder, one for every configuration folder. Every later instance will forward its command-line argument to the running instance, then exit.
+pub struct Instance {
+    /// socket listener, and socket path.
+    #[cfg(unix)]
+    listener: Option<(UnixListener, PathBuf)>,
+    /// lock file, held for as long as the instance is running. released by the system, even if the instance doesn't exit cleanly.
+    #[cfg(unix)]
+    lock: Option<File>,
+}
+
+impl Instance {
+    #[cfg(unix)]
+    const PATH_SOCKET: &str = "melodix";
+    /// number of connection attempts to the running instance, which may still be starting.
+    #[cfg(unix)]
+    const FORWARD_COUNT: usize = 20;
+    #[cfg(unix)]
+    const FORWARD_DELAY: Duration = Duration::from_millis(100);
+
+    /// Try locking the single instance. Returns none if another instance is already running, after forwarding the argument to it.
+    #[cfg(unix)]
+    pub fn new(argument: &Argument) -> Option<Self> {
+        let unlocked = Self {
+            listener: None,
+            lock: None,
+        };
+
+        // without a runtime folder, run without a single-instance lock.
+        let path = match Self::get_path(Self::PATH_SOCKET) {
+            Ok(path) => path,
+            Err(error) => {
+                App::error(&error.to_string());
+                return Some(unlocked);
+            }
+        };
+
+        // without a lock file, run without a single-instance lock.
+        let Ok(lock) = File::create(path.with_extension("lock")) else {
+            return Some(unlocked);
+        };
+
+        match lock.try_lock() {
+            Ok(()) => {
+                // only the lock holder can own the socket, so any socket left behind is from an instance that didn't exit cleanly.
+                let _ = std::fs::remove_file(&path);
+
+                // only the user can connect, rather than every user by the default permission.
+                let listener = UnixListener::bind(&path).ok().filter(|_| {
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).is_ok()
+                });
+
+                Some(Self {
+                    listener: listener.map(|x| (x, path)),
+                    lock: Some(lock),
+                })
+            }
+            Err(TryLockError::WouldBlock) => {
+                if let Err(error) = Self::forward(&path, argument) {
+                    App::error(&error.to_string());
+                }
+
+                None
+            }
+            Err(TryLockError::Error(_)) => Some(unlocked),
+        }
+    }
+
+    /// Get a socket path by name, i.e. for the single instance or the control server. An alternate configuration folder has its own socket.
+    #[cfg(unix)]
+    pub fn get_path(name: &str) -> anyhow::Result<PathBuf> {
+        let name = match App::get_configuration_folder() {
+            Some(path) => format!("{name}.{:016x}", App::get_hash(path.as_bytes())),
+            None => name.to_string(),
+        };
+
+        App::get_runtime_path(&format!("{name}.socket"))
+    }
+
+    /// Check if this instance holds the single-instance lock, so that it owns every socket of its configuration folder.
+    #[cfg(unix)]
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// Forward the argument to the running instance, which may still be starting.
+    #[cfg(unix)]
+    fn forward(path: &Path, argument: &Argument) -> anyhow::Result<()> {
+        for _ in 0..Self::FORWARD_COUNT {
+            if let Ok(mut stream) = UnixStream::connect(path) {
+                stream.write_all(&postcard::to_allocvec(&argument.get_forward())?)?;
+
+                return Ok(());
+            }
+
+            std::thread::sleep(Self::FORWARD_DELAY);
+        }
+
+        Err(anyhow::anyhow!(
+            "Melodix is already running, but couldn't be reached at \"{}\".",
+            path.display()
+        ))
+    }
+
+    #[cfg(not(unix))]
+    pub fn new(_: &Argument) -> Option<Self> {
+        Some(Self {})
+    }
+
+    /// Listen for every forwarded argument, on a separate thread.
+    #[cfg(unix)]
+    pub fn listen(&self, context: egui::Context) -> Option<Receiver<Argument>> {
+        let (listener, _) = self.listener.as_ref()?;
+        let listener = listener.try_clone().ok()?;
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut data = Vec::new();
+
+                if stream.read_to_end(&mut data).is_ok()
+                    && let Ok(argument) = postcard::from_bytes::<Argument>(&data)
+                {
+                    if tx.send(argument).is_err() {
+                        break;
+                    }
+
+                    context.request_repaint();
+                }
+            }
+        });
+
+        Some(rx)
+    }
+
+    #[cfg(not(unix))]
+    pub fn listen(&self, _: egui::Context) -> Option<Receiver<Argument>> {
+        None
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Instance {
+    fn drop(&mut self) {
+        if let Some((_, path)) = &self.listener {
+            let _ = std::fs::remove_file(path);
+        }
+
+        // release the lock only after the socket is gone, so that the socket of a new instance is never removed.
+        if let Some(lock) = &self.lock {
+            let _ = lock.unlock();
+        }
+    }
+}
@@ -48,15 +48,24 @@
 * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
-use crate::app::*;
+use crate::{chapter::*, cue::*, store::*};
 
 //================================================================
 
 use rodio::Source;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, io::BufReader, path::Path, time::Duration};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use symphonia::core::{
-    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, Tag},
+    probe::Hint,
 };
 use walkdir::{DirEntry, WalkDir};
 
@@ -69,51 +78,36 @@ pub struct Library {
     pub list_group: Vec<Group>,
     #[serde(skip)]
     pub list_shown: (Vec<usize>, Vec<usize>, Vec<usize>),
+    /// track index, for group, album, track, keyed by the track key (see Track::get_key).
+    #[serde(skip)]
+    pub list_key: HashMap<String, (usize, usize, usize)>,
+    /// track index, keyed by the normalized track key (see Library::get_path_key). built on first use.
+    #[serde(skip)]
+    pub list_path: HashMap<String, (usize, usize, usize)>,
+    /// browse node list, for every browse mode other than group (see Library::make_node).
+    #[serde(skip)]
+    pub list_node: Vec<Node>,
+    /// folder tree, for the folder layout (see Library::make_folder). built on first use.
+    #[serde(skip)]
+    pub list_folder: Vec<Folder>,
 }
 
 impl Library {
-    const PATH_LIBRARY: &'static str = "library.data";
-
     pub fn new() -> Self {
-        if let Ok(file) = std::fs::read(App::get_configuration_path(Self::PATH_LIBRARY, false))
-            && let Ok(library) = postcard::from_bytes::<Self>(&file)
-        {
-            return Self {
-                list_shown: (
-                    (0..library.list_group.len()).collect(),
-                    Vec::default(),
-                    Vec::default(),
-                ),
-                list_group: library.list_group,
-            };
-        }
+        let library = Self::load();
 
-        Self::default()
+        Self {
+            list_shown: (library.get_group_order(), Vec::default(), Vec::default()),
+            list_key: Self::make_key(&library.list_group),
+            list_path: HashMap::default(),
+            list_node: Vec::default(),
+            list_folder: Vec::default(),
+            list_group: library.list_group,
+        }
     }
 
     pub fn scan(path: &str) -> Self {
-        let path: Vec<walkdir::DirEntry> = WalkDir::new(path)
-            .into_iter()
-            .filter_map(|x| {
-                let x = x.expect("Library::scan(): Couldn't obtain directory entry.");
-
-                if x.file_type().is_file() {
-                    if let Some(extension) = x.path().extension()
-                        // in the interest of speed, just check for extension rather than an actual file type check.
-                        && (extension == "mp3" || extension == "flac" || extension == "wav")
-                    {
-                        Some(x)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        let mut track_list: Vec<(String, String, Track)> =
-            path.par_iter().filter_map(Track::new).collect();
+        let mut track_list = Self::read(path);
 
         track_list.par_sort_by(|(_, a_album, a_track), (_, b_album, b_track)| {
             let a_track = a_track.track.unwrap_or_default();
@@ -137,7 +131,9 @@ impl Library {
 
         let mut list_group: Vec<Group> = map_group.into_values().collect();
 
-        list_group.par_sort_by(|a, b| a.name.cmp(&b.name));
+        list_group.par_sort_by(|a, b| {
+            Self::natural_order(Self::get_sort_name(&a.name), Self::get_sort_name(&b.name))
+        });
 
         let library = Self {
             list_shown: (
@@ -145,15 +141,621 @@ impl Library {
                 Vec::default(),
                 Vec::default(),
             ),
+            list_key: Self::make_key(&list_group),
+            list_path: HashMap::default(),
+            list_node: Vec::default(),
+            list_folder: Vec::default(),
             list_group,
         };
 
-        let serialize: Vec<u8> = postcard::to_allocvec(&library).unwrap();
-
-        std::fs::write(App::get_configuration_path(Self::PATH_LIBRARY, false), serialize).unwrap();
+        library.save();
 
         library
     }
+
+    /// Add every track in a file or folder to the library, for the current session only. Returns every track in the path, in path order, including any track that was already in the library.
+    pub fn insert(&mut self, path: &str) -> Vec<(usize, usize, usize)> {
+        let mut list = Vec::new();
+
+        for (group, album, track) in Self::read(path) {
+            let key = track.get_key();
+
+            if let Some(entry) = self.find_entry(&key) {
+                list.push(entry);
+                continue;
+            }
+
+            // every new group, album and track is pushed to the end, so every existing index is kept.
+            let i_group = match self.list_group.iter().position(|x| x.name == group) {
+                Some(i_group) => i_group,
+                None => {
+                    self.list_group.push(Group {
+                        name: group,
+                        list_album: vec![],
+                    });
+                    self.list_group.len() - 1
+                }
+            };
+
+            let group = &mut self.list_group[i_group];
+            group.insert_track(&album, track);
+
+            let i_album = group
+                .list_album
+                .iter()
+                .position(|x| x.name == album)
+                .unwrap();
+            let i_track = group.list_album[i_album].list_track.len() - 1;
+            let entry = (i_group, i_album, i_track);
+
+            if !self.list_path.is_empty() {
+                self.list_path.insert(Self::get_path_key(&key), entry);
+            }
+
+            self.list_key.insert(key, entry);
+            list.push(entry);
+        }
+
+        // re-build the folder tree on next use.
+        self.list_folder.clear();
+
+        list.sort_by(|a, b| self.compare_path(*a, *b));
+        list
+    }
+
+    /// Read every track in a file or folder, as a group name, album name, and track.
+    fn read(path: &str) -> Vec<(String, String, Track)> {
+        let path: Vec<walkdir::DirEntry> = WalkDir::new(path)
+            .into_iter()
+            // skip every entry that can't be read, i.e. a folder without permission.
+            .filter_map(Result::ok)
+            .filter_map(|x| {
+                if x.file_type().is_file() {
+                    if let Some(extension) = x.path().extension()
+                        // in the interest of speed, just check for extension rather than an actual file type check.
+                        && (Track::FORMAT_LIST.iter().any(|x| extension == *x) || extension == "cue")
+                    {
+                        Some(x)
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let (cue, path): (Vec<DirEntry>, Vec<DirEntry>) = path
+            .into_iter()
+            .partition(|x| x.path().extension().is_some_and(|x| x == "cue"));
+
+        // every CUE sheet track is a virtual track into a shared audio file.
+        let cue: Vec<(String, String, Track)> = cue
+            .par_iter()
+            .flat_map(|x| Track::from_cue(x.path()))
+            .collect();
+
+        // do not scan an audio file that is already used by a CUE sheet.
+        let skip: HashSet<&str> = cue.iter().map(|(_, _, x)| x.path.as_str()).collect();
+
+        let path: Vec<DirEntry> = path
+            .into_iter()
+            .filter(|x| !skip.contains(x.path().to_str().unwrap_or_default()))
+            .collect();
+
+        let mut track_list: Vec<(String, String, Track)> =
+            path.par_iter().filter_map(Track::new).collect();
+
+        track_list.extend(cue);
+        track_list
+    }
+
+    /// Make every browse node for a browse mode, sorted by name. Every node keeps the library order. Browse by folder is in path order instead (see Library::make_folder_node).
+    pub fn make_node(&self, browse: Browse) -> Vec<Node> {
+        if browse == Browse::Folder {
+            let mut list_node = Vec::new();
+
+            for folder in self.make_folder() {
+                self.make_folder_node(&folder, 0, &mut list_node);
+            }
+
+            return list_node;
+        }
+
+        let mut list_node: Vec<Node> = Vec::new();
+        let mut map_node: HashMap<String, usize> = HashMap::new();
+
+        for (i_group, group) in self.list_group.iter().enumerate() {
+            for (i_album, album) in group.list_album.iter().enumerate() {
+                for (i_track, track) in album.list_track.iter().enumerate() {
+                    // every node for the track, as a node key and a node name.
+                    let list: Vec<(String, String)> = match browse {
+                        Browse::Group => vec![(group.name.clone(), group.name.clone())],
+                        Browse::Genre => track.get_genre(),
+                        Browse::Year => match track.get_year() {
+                            Some(year) => {
+                                let name = format!("{}s", year - year % 10);
+                                vec![(name.clone(), name)]
+                            }
+                            None => vec![(String::default(), "< Unknown Year >".to_string())],
+                        },
+                        Browse::Folder => unreachable!(),
+                    };
+
+                    for (key, name) in list {
+                        let index = *map_node.entry(key).or_insert_with(|| {
+                            list_node.push(Node {
+                                name,
+                                ..Default::default()
+                            });
+                            list_node.len() - 1
+                        });
+
+                        let node = &mut list_node[index];
+
+                        node.list_track.push((i_group, i_album, i_track));
+
+                        // every track in an album is visited in a row, so only check the last album.
+                        if node.list_album.last() != Some(&(i_group, i_album)) {
+                            node.list_album.push((i_group, i_album));
+                        }
+                    }
+                }
+            }
+        }
+
+        list_node.sort_by(|a, b| Self::natural_order(&a.name, &b.name));
+
+        if browse == Browse::Year {
+            for node in &mut list_node {
+                node.list_album.sort_by_key(|(i_group, i_album)| {
+                    let album = &self.list_group[*i_group].list_album[*i_album];
+                    album.list_track.iter().find_map(|x| x.get_year())
+                });
+            }
+        }
+
+        list_node
+    }
+
+    /// Make a browse node for a folder and, right after it, for every sub-folder, from the same folder tree as the folder layout. Every node has every track in the folder and every sub-folder, in path order.
+    fn make_folder_node(&self, folder: &Folder, depth: usize, list_node: &mut Vec<Node>) {
+        let list_track = folder.get_track(self);
+        let mut list_album = Vec::new();
+
+        for (i_group, i_album, _) in &list_track {
+            if !list_album.contains(&(*i_group, *i_album)) {
+                list_album.push((*i_group, *i_album));
+            }
+        }
+
+        list_node.push(Node {
+            name: folder.name.clone(),
+            depth,
+            list_album,
+            list_track,
+        });
+
+        for child in &folder.list_folder {
+            self.make_folder_node(child, depth + 1, list_node);
+        }
+    }
+
+    /// Make the folder tree from the path of every track. Every folder keeps its sub-folders and tracks in path order, and every root folder with only a single sub-folder is merged into it.
+    pub fn make_folder(&self) -> Vec<Folder> {
+        let mut list: Vec<(usize, usize, usize)> = Vec::new();
+
+        for (i_group, group) in self.list_group.iter().enumerate() {
+            for (i_album, album) in group.list_album.iter().enumerate() {
+                for i_track in 0..album.list_track.len() {
+                    list.push((i_group, i_album, i_track));
+                }
+            }
+        }
+
+        list.sort_by(|a, b| self.compare_path(*a, *b));
+
+        // every open folder, from the (unnamed) tree root to the folder of the last track.
+        let mut stack = vec![Folder::default()];
+
+        for entry in list {
+            let track = &self.list_group[entry.0].list_album[entry.1].list_track[entry.2];
+            let path = Path::new(&track.path).parent().unwrap_or(Path::new(""));
+            let path: Vec<String> = path
+                .components()
+                .map(|x| x.as_os_str().to_string_lossy().to_string())
+                .collect();
+
+            // the track list is in path order, so a folder is never re-opened once closed.
+            let depth = path
+                .iter()
+                .zip(stack.iter().skip(1))
+                .take_while(|(a, b)| **a == b.name)
+                .count();
+
+            while stack.len() > depth + 1 {
+                let folder = stack.pop().unwrap();
+                stack.last_mut().unwrap().list_folder.push(folder);
+            }
+
+            for name in &path[depth..] {
+                stack.push(Folder {
+                    name: name.clone(),
+                    ..Default::default()
+                });
+            }
+
+            stack.last_mut().unwrap().list_track.push(entry);
+        }
+
+        while stack.len() > 1 {
+            let folder = stack.pop().unwrap();
+            stack.last_mut().unwrap().list_folder.push(folder);
+        }
+
+        let root = stack.pop().unwrap();
+
+        root.list_folder
+            .into_iter()
+            .map(|mut folder| {
+                // show every library root by its full path, rather than every folder leading to it.
+                while folder.list_track.is_empty() && folder.list_folder.len() == 1 {
+                    let child = folder.list_folder.pop().unwrap();
+
+                    folder = Folder {
+                        name: Path::new(&folder.name)
+                            .join(&child.name)
+                            .display()
+                            .to_string(),
+                        ..child
+                    };
+                }
+
+                folder
+            })
+            .collect()
+    }
+
+    /// Compare two track in path order: every path component is compared in natural order, and every CUE sheet track in the same file by offset.
+    pub fn compare_path(&self, a: (usize, usize, usize), b: (usize, usize, usize)) -> Ordering {
+        let a = &self.list_group[a.0].list_album[a.1].list_track[a.2];
+        let b = &self.list_group[b.0].list_album[b.1].list_track[b.2];
+
+        let mut path_a = Path::new(&a.path).components();
+        let mut path_b = Path::new(&b.path).components();
+
+        loop {
+            match (path_a.next(), path_b.next()) {
+                (None, None) => return a.offset.cmp(&b.offset),
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(x), Some(y)) => {
+                    let order = Self::natural_order(
+                        &x.as_os_str().to_string_lossy(),
+                        &y.as_os_str().to_string_lossy(),
+                    );
+
+                    if order != Ordering::Equal {
+                        return order;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Compare two text in natural order: case-insensitive, and every number is compared by value (i.e. "Track 2" comes before "Track 10").
+    pub fn natural_order(a: &str, b: &str) -> Ordering {
+        let mut a = a.chars().flat_map(char::to_lowercase).peekable();
+        let mut b = b.chars().flat_map(char::to_lowercase).peekable();
+
+        loop {
+            match (a.peek().copied(), b.peek().copied()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                    let mut x = String::new();
+                    let mut y = String::new();
+
+                    while let Some(digit) = a.next_if(char::is_ascii_digit) {
+                        x.push(digit);
+                    }
+                    while let Some(digit) = b.next_if(char::is_ascii_digit) {
+                        y.push(digit);
+                    }
+
+                    let x = x.trim_start_matches('0');
+                    let y = y.trim_start_matches('0');
+
+                    // a longer number without any leading zero is always greater.
+                    let order = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+
+                    if order != Ordering::Equal {
+                        return order;
+                    }
+                }
+                (Some(x), Some(y)) => {
+                    if x != y {
+                        return x.cmp(&y);
+                    }
+
+                    a.next();
+                    b.next();
+                }
+            }
+        }
+    }
+
+    /// Get every group index, in group order (see Library::get_sort_name), which may not be the library order, i.e. for a library from an older version, or a group added for the current session only.
+    pub fn get_group_order(&self) -> Vec<usize> {
+        let mut list: Vec<usize> = (0..self.list_group.len()).collect();
+
+        list.sort_by(|a, b| {
+            Self::natural_order(
+                Self::get_sort_name(&self.list_group[*a].name),
+                Self::get_sort_name(&self.list_group[*b].name),
+            )
+        });
+
+        list
+    }
+
+    /// Get the name to sort a group by, without any leading article (i.e. "The Beatles" sorts as "Beatles").
+    pub fn get_sort_name(name: &str) -> &str {
+        for article in ["the ", "a ", "an "] {
+            if let Some(prefix) = name.get(0..article.len())
+                && prefix.eq_ignore_ascii_case(article)
+            {
+                return name[article.len()..].trim_start();
+            }
+        }
+
+        name
+    }
+
+    /// Compare two tracks (group, album, track) by a sort kind. Ties are broken by library order.
+    pub fn compare(
+        &self,
+        a: (usize, usize, usize),
+        b: (usize, usize, usize),
+        sort: Sort,
+    ) -> Ordering {
+        let get = |x: (usize, usize, usize)| {
+            let group = &self.list_group[x.0];
+            let album = &group.list_album[x.1];
+            (group, album, &album.list_track[x.2])
+        };
+
+        let (a_group, a_album, a_track) = get(a);
+        let (b_group, b_album, b_track) = get(b);
+
+        let text = |a: Option<&str>, b: Option<&str>| {
+            Self::natural_order(a.unwrap_or_default(), b.unwrap_or_default())
+        };
+
+        let order = match sort {
+            Sort::Number => a_track.track.cmp(&b_track.track),
+            Sort::Title => Self::natural_order(&a_track.name, &b_track.name),
+            Sort::Group => Self::natural_order(
+                Self::get_sort_name(&a_group.name),
+                Self::get_sort_name(&b_group.name),
+            ),
+            Sort::Album => Self::natural_order(&a_album.name, &b_album.name),
+            Sort::Genre => text(a_track.kind.as_deref(), b_track.kind.as_deref()),
+            Sort::Date => text(a_track.date.as_deref(), b_track.date.as_deref()),
+            Sort::Time => a_track.time.cmp(&b_track.time),
+        };
+
+        order.then(a.cmp(&b))
+    }
+
+    /// Get a track by its track key (see Track::get_key), as a group, album, track index.
+    pub fn get_entry(&self, key: &str) -> Option<(usize, usize, usize)> {
+        self.list_key.get(key).copied()
+    }
+
+    /// Get a track by a track key that may not match the library verbatim, i.e. a different case, path separator, or a path through a symbolic link.
+    pub fn find_entry(&mut self, key: &str) -> Option<(usize, usize, usize)> {
+        if let Some(entry) = self.get_entry(key) {
+            return Some(entry);
+        }
+
+        if self.list_path.is_empty() {
+            self.list_path = self
+                .list_key
+                .par_iter()
+                .map(|(key, entry)| (Self::get_path_key(key), *entry))
+                .collect();
+        }
+
+        if let Some(entry) = self.list_path.get(&Self::get_path_key(key)) {
+            return Some(*entry);
+        }
+
+        // a path through a symbolic link. only resolve every track with the same file name, rather than the whole library.
+        let (path, fragment) = Self::split_key(key);
+        let real = std::fs::canonicalize(path).ok()?;
+        let name = |path: &Path| path.file_name().map(|x| x.to_string_lossy().to_lowercase());
+        let list_name = [name(Path::new(path)), name(&real)];
+
+        self.list_key.iter().find_map(|(other, entry)| {
+            let (other, other_fragment) = Self::split_key(other);
+
+            (other_fragment == fragment
+                && list_name.contains(&name(Path::new(other)))
+                && std::fs::canonicalize(other).is_ok_and(|x| x == real))
+            .then_some(*entry)
+        })
+    }
+
+    /// Normalize a track key, for comparison: use "/" as the path separator, and ignore case.
+    pub fn get_path_key(key: &str) -> String {
+        let (path, fragment) = Self::split_key(key);
+
+        format!("{}{fragment}", path.replace('\\', "/").to_lowercase())
+    }
+
+    /// Split a track key into the audio file path and the CUE sheet media fragment, if any (see Track::get_key). The fragment time is normalized, i.e. "#t=243.360" is "#t=243.36".
+    fn split_key(key: &str) -> (&str, String) {
+        key.rsplit_once("#t=")
+            .and_then(|(path, time)| Some((path, format!("#t={}", time.parse::<f64>().ok()?))))
+            .unwrap_or((key, String::default()))
+    }
+
+    fn make_key(list_group: &[Group]) -> HashMap<String, (usize, usize, usize)> {
+        let mut list_key = HashMap::new();
+
+        for (i_group, group) in list_group.iter().enumerate() {
+            for (i_album, album) in group.list_album.iter().enumerate() {
+                for (i_track, track) in album.list_track.iter().enumerate() {
+                    list_key.insert(track.get_key(), (i_group, i_album, i_track));
+                }
+            }
+        }
+
+        list_key
+    }
+}
+
+impl Store for Library {
+    const PATH: &'static str = "library.data";
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, data: &[u8]) -> Option<Self> {
+        if version != 0 {
+            return None;
+        }
+
+        if let Some(library) = decode(data) {
+            return Some(library);
+        }
+
+        let legacy: LibraryLegacy = decode(data)?;
+
+        let list_group = legacy
+            .list_group
+            .into_iter()
+            .map(|group| Group {
+                name: group.name,
+                list_album: group
+                    .list_album
+                    .into_iter()
+                    .map(|album| Album {
+                        name: album.name,
+                        icon: album.icon,
+                        list_track: album
+                            .list_track
+                            .into_iter()
+                            .map(|track| Track {
+                                name: track.name,
+                                path: track.path,
+                                time: track.time,
+                                date: track.date,
+                                kind: track.kind,
+                                icon: track.icon,
+                                track: track.track,
+                                chapter: Vec::default(),
+                                offset: None,
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Some(Self {
+            list_group,
+            ..Self::default()
+        })
+    }
+}
+
+/// library layout from before there was a layout version, for migration.
+#[derive(Deserialize)]
+struct LibraryLegacy {
+    list_group: Vec<GroupLegacy>,
+}
+
+#[derive(Deserialize)]
+struct GroupLegacy {
+    name: String,
+    list_album: Vec<AlbumLegacy>,
+}
+
+#[derive(Deserialize)]
+struct AlbumLegacy {
+    name: String,
+    icon: Option<String>,
+    list_track: Vec<TrackLegacy>,
+}
+
+#[derive(Deserialize)]
+struct TrackLegacy {
+    name: String,
+    path: String,
+    time: Duration,
+    date: Option<String>,
+    kind: Option<String>,
+    icon: (Option<Vec<u8>>, Option<(u32, u32)>),
+    track: Option<usize>,
+}
+
+/// library browse mode, for the left library panel.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Browse {
+    Group,
+    Genre,
+    /// browse by decade.
+    Year,
+    /// browse by folder hierarchy, in path order.
+    Folder,
+}
+
+/// library browse node, i.e. a genre, a decade or a folder.
+#[derive(Default, Clone)]
+pub struct Node {
+    pub name: String,
+    /// depth in the folder hierarchy, for browse by folder. always zero otherwise.
+    pub depth: usize,
+    /// every album with at least one track in the node, for group, album.
+    pub list_album: Vec<(usize, usize)>,
+    /// every track in the node, for group, album, track.
+    pub list_track: Vec<(usize, usize, usize)>,
+}
+
+/// library folder, for the folder layout.
+#[derive(Default, Clone)]
+pub struct Folder {
+    pub name: String,
+    pub list_folder: Vec<Folder>,
+    /// every track directly in the folder, for group, album, track.
+    pub list_track: Vec<(usize, usize, usize)>,
+}
+
+impl Folder {
+    /// Get every track in the folder and every sub-folder, in path order.
+    pub fn get_track(&self, library: &Library) -> Vec<(usize, usize, usize)> {
+        let mut list = self.list_track.clone();
+
+        for folder in &self.list_folder {
+            list.extend(folder.get_track(library));
+        }
+
+        list.sort_by(|a, b| library.compare_path(*a, *b));
+        list
+    }
+}
+
+/// table column to sort by.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Sort {
+    Number,
+    Title,
+    Group,
+    Album,
+    Genre,
+    Date,
+    Time,
 }
 
 //================================================================
@@ -217,9 +819,15 @@ pub struct Track {
     pub kind: Option<String>,
     pub icon: (Option<Vec<u8>>, Option<(u32, u32)>),
     pub track: Option<usize>,
+    pub chapter: Vec<Chapter>,
+    /// start time into the audio file, for a track from a CUE sheet.
+    pub offset: Option<Duration>,
 }
 
 impl Track {
+    /// every supported file extension.
+    pub const FORMAT_LIST: [&str; 5] = ["mp3", "flac", "wav", "m4a", "m4b"];
+
     const GENRE_LIST: [&str; 192] = [
         "Blues",
         "Classic Rock",
@@ -415,6 +1023,70 @@ impl Track {
         "Psybient",
     ];
 
+    /// Get the track year, from the first four digits of the date.
+    pub fn get_year(&self) -> Option<u32> {
+        self.date.as_ref()?.get(0..4)?.parse().ok()
+    }
+
+    /// Get every genre of the track, as a normalized genre key and a genre name. A multi-valued genre is split, i.e. "Rock; Pop" or "Rock|Pop".
+    pub fn get_genre(&self) -> Vec<(String, String)> {
+        let mut list = Vec::new();
+
+        if let Some(kind) = &self.kind {
+            for name in kind.split(['|', ';', ',', '/']).map(str::trim) {
+                // "Hip-Hop", "hip hop" and "HipHop" are the same genre.
+                let key: String = name
+                    .chars()
+                    .filter(|x| x.is_alphanumeric())
+                    .flat_map(char::to_lowercase)
+                    .collect();
+
+                if !key.is_empty() && !list.iter().any(|(x, _)| *x == key) {
+                    list.push((key, name.to_string()));
+                }
+            }
+        }
+
+        if list.is_empty() {
+            list.push((String::default(), "< Unknown Genre >".to_string()));
+        }
+
+        list
+    }
+
+    /// Check if the track has a given genre. Case-insensitive.
+    pub fn has_genre(&self, genre: &str) -> bool {
+        if let Some(kind) = &self.kind {
+            return kind
+                .split('|')
+                .any(|entry| entry.trim().eq_ignore_ascii_case(genre));
+        }
+
+        false
+    }
+
+    /// Get a unique key for the track. Every track from the same CUE sheet shares a path, so the start time is appended as a media fragment.
+    pub fn get_key(&self) -> String {
+        match self.offset {
+            Some(offset) => format!("{}#t={}", self.path, offset.as_secs_f64()),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Get the index of the chapter at a given play position, if the track has any chapter.
+    pub fn get_chapter(&self, time: Duration) -> Option<usize> {
+        if self.chapter.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.chapter
+                .iter()
+                .rposition(|x| x.time <= time)
+                .unwrap_or_default(),
+        )
+    }
+
     fn get_track_time(path: &Path) -> Duration {
         // rodio can never retrieve the duration for an .MP3 file, so we test this first.
         if let Some(extension) = path.extension()
@@ -437,6 +1109,63 @@ impl Track {
         Duration::default()
     }
 
+    /// Find the audio file of a CUE sheet entry. A CUE sheet will often point to the original rip (i.e. a .wav file) of an audio file that has since been converted, so fall back to the same file name with any supported extension.
+    fn get_cue_file(path: &Path) -> Option<PathBuf> {
+        if path.is_file() {
+            return Some(path.to_path_buf());
+        }
+
+        Self::FORMAT_LIST
+            .iter()
+            .map(|extension| path.with_extension(extension))
+            .find(|path| path.is_file())
+    }
+
+    fn from_cue(path: &Path) -> Vec<(String, String, Track)> {
+        let Ok(sheet) = CueSheet::new(path) else {
+            return Vec::default();
+        };
+
+        let mut list = Vec::new();
+
+        for (i, entry) in sheet.list_track.iter().enumerate() {
+            // skip any entry with a missing audio file.
+            let Some(file) = Self::get_cue_file(Path::new(&entry.path)) else {
+                continue;
+            };
+
+            // a track ends where the next track on the same file starts, or at the end of the file.
+            let time = match sheet.list_track.get(i + 1) {
+                Some(next) if next.path == entry.path => next.time,
+                _ => Self::get_track_time(&file),
+            };
+
+            let group = sheet.group.clone().or_else(|| entry.group.clone());
+            let name = entry.name.clone().unwrap_or_else(|| entry.path.clone());
+
+            list.push((
+                group.unwrap_or_else(|| "< Unknown Group >".to_string()),
+                sheet
+                    .album
+                    .clone()
+                    .unwrap_or_else(|| "< Unknown Album >".to_string()),
+                Track {
+                    name,
+                    path: file.display().to_string(),
+                    time: time.saturating_sub(entry.time),
+                    date: sheet.date.clone(),
+                    kind: sheet.kind.clone(),
+                    icon: (None, None),
+                    track: entry.track,
+                    chapter: Vec::default(),
+                    offset: Some(entry.time),
+                },
+            ));
+        }
+
+        list
+    }
+
     fn new(path: &DirEntry) -> Option<(String, String, Track)> {
         let path = path.path();
 
@@ -470,6 +1199,8 @@ impl Track {
                 time: Self::get_track_time(path),
                 icon: (None, None),
                 track: None,
+                chapter: Vec::default(),
+                offset: None,
             };
 
             if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
@@ -538,6 +1269,24 @@ impl Track {
                 }
             }
 
+            // collect every tag, both from outside and inside of the container, for chapter scanning.
+            let mut tag_list: Vec<Tag> = Vec::new();
+
+            if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+                tag_list.extend_from_slice(revision.tags());
+            }
+
+            if let Some(revision) = probed.format.metadata().current() {
+                tag_list.extend_from_slice(revision.tags());
+            }
+
+            let rate = probed
+                .format
+                .default_track()
+                .and_then(|x| x.codec_params.sample_rate);
+
+            file_track.chapter = Chapter::scan(path, &tag_list, probed.format.cues(), rate);
+
             return Some((
                 file_group.unwrap_or_else(|| "< Unknown Group >".to_string()),
                 file_album.unwrap_or_else(|| "< Unknown Album >".to_string()),
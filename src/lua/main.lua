@@ -67,7 +67,7 @@ function plug_in.close(self)
 end
 
 function plug_in.tick(self)
-    -- This method will be ran on every render frame. A render frame will happen after 1 second, or on user input.
+    -- This method will be ran every second while a track is playing. With a hidden window, it may run late (see plug_in in meta.lua).
 end
 
 function plug_in.seek(self, time)
@@ -96,6 +96,10 @@ function plug_in.skip_b(self)
     print("Skip + call-back.")
 end
 
+function plug_in.queue(self)
+    print("Queue call-back. Queue length: " .. tostring(#melodix.get_queue()))
+end
+
 function plug_in.pause(self, time)
     local group, album, track = melodix.get_state()
 
@@ -24,15 +24,23 @@ album = {}
 
 ---Track class.
 ---@class track
----@field name   string       # Track name.
----@field path   string       # Track path. Absolute path to the track file.
----@field time   time         # Track time.
----@field date   string | nil # Track date. *May* be nil.
----@field kind   string | nil # Track kind. *May* be nil.
----@field icon   table        # Track icon. A table where the first element *may* be the album cover data and the second element *may* be the dimension of the album cover, if they are present in the meta-data.
----@field track  number | nil # Track number. *May* be nil.
+---@field name    string       # Track name.
+---@field path    string       # Track path. Absolute path to the track file.
+---@field time    time         # Track time.
+---@field date    string | nil # Track date. *May* be nil.
+---@field kind    string | nil # Track kind. *May* be nil.
+---@field icon    table        # Track icon. A table where the first element *may* be the album cover data and the second element *may* be the dimension of the album cover, if they are present in the meta-data.
+---@field track   number | nil # Track number. *May* be nil.
+---@field chapter table        # Chapter list. A table array of each chapter; empty if the track has no chapter.
+---@field offset  time | nil   # Track offset. Start time into the audio file, for a track from a CUE sheet. *May* be nil.
 track = {}
 
+---Chapter class.
+---@class chapter
+---@field name string # Chapter name.
+---@field time time   # Chapter start time.
+chapter = {}
+
 ---Time class.
 ---@class time
 ---@field secs number # Time in seconds.
@@ -46,6 +54,22 @@ TOAST_KIND = {
     SUCCESS = 3,
 }
 
+---Repeat kind.
+---@enum repeat_kind
+REPEAT_KIND = {
+    OFF = 0, -- Stop at the end of the queue.
+    ONE = 1, -- Repeat the current track.
+    ALL = 2, -- Repeat the queue.
+}
+
+---Stop kind.
+---@enum stop_kind
+STOP_KIND = {
+    OFF   = 0, -- Do not stop.
+    TRACK = 1, -- Stop after the current track.
+    ALBUM = 2, -- Stop after the current album.
+}
+
 ---Get the library.
 ---@return library library # Library.
 function melodix.get_library() end
@@ -61,8 +85,70 @@ function melodix.get_state() end
 ---@return number index # Index into the queue as the current entry.
 function melodix.get_queue() end
 
+---Search the library, in the same way as the library search bar. Will raise an error on a bad query.
+---Plain text will match any field. A field filter can be one of artist:, album:, title:, genre:, path: (text), year: (1997, >1997, 1990..1999) or time: (<5m, >3:30, 90s).
+---@param query string # Search query, i.e. "artist:radiohead year:>1997 genre:rock time:<5m".
+---@return table search # A table array containing a table where the first element is the group index, the second element is the album index, and the last index is the track index. Better matches come first.
+function melodix.get_search(query) end
+
 ---Get the currently playing group, album and track data.
 ---@param kind toast_kind # Toast kind.
 ---@param text string     # Toast text.
 ---@param time number     # Toast time.
 function melodix.set_toast(kind, text, time) end
+
+---Get the repeat kind.
+---@return repeat_kind kind # Repeat kind.
+function melodix.get_repeat() end
+
+---Set the repeat kind.
+---@param kind repeat_kind # Repeat kind.
+function melodix.set_repeat(kind) end
+
+---Get the stop kind.
+---@return stop_kind kind # Stop kind.
+function melodix.get_stop() end
+
+---Set the stop kind. Will reset to STOP_KIND.OFF once the app has stopped.
+---@param kind stop_kind # Stop kind.
+function melodix.set_stop(kind) end
+
+---Module call-back. Every call-back is optional, and is called with the module table itself (i.e. `function plug_in.play(self, time)`).
+---Every call-back runs on the app's own step: with a window, a step only runs when the window is updated, which may be late or paused while the window is hidden. Play-back doesn't wait on it, and will move on to the next track on its own; every call-back (i.e. for every track played in the meantime) will then run in order on the next step, so the play state read from within a call-back may already be ahead of it. When running headless, a step runs as soon as there is an event.
+---@class plug_in
+plug_in = {}
+
+---Called once, on the first step.
+function plug_in.begin(self) end
+
+---Called once, when the app is closing.
+function plug_in.close(self) end
+
+---Called every second while a track is playing. Every tick for a track that the app has since moved past is skipped.
+---@param time number # Play position, in seconds.
+function plug_in.tick(self, time) end
+
+---Called on a seek.
+---@param time number # Seek position, in seconds.
+function plug_in.seek(self, time) end
+
+---Called when a track begins play-back, or when play-back is resumed.
+---For a new track, this is called on the step after the track was started rather than from within the start itself, so the track is already playing: the time is the play position read at that step, and *may* be past 0 (i.e. when resuming a bookmark).
+---@param time number | nil # Play position, in seconds. *May* be nil, when resumed by a play (rather than toggle) request.
+function plug_in.play(self, time) end
+
+---Called when play-back is paused.
+---@param time number | nil # Play position, in seconds. *May* be nil, when paused by a pause (rather than toggle) request.
+function plug_in.pause(self, time) end
+
+---Called when play-back is stopped.
+function plug_in.stop(self) end
+
+---Called when skipping to the previous track.
+function plug_in.skip_a(self) end
+
+---Called when skipping to the next track.
+function plug_in.skip_b(self) end
+
+---Called once per step after the queue was modified, no matter how many modifications were made.
+function plug_in.queue(self) end
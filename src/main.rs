@@ -49,20 +49,62 @@
 */
 
 mod app;
+mod argument;
+mod bookmark;
+mod chapter;
+mod control;
+mod cue;
+mod instance;
 mod library;
+mod mpd;
+mod player;
+mod playlist;
+mod queue;
+mod remote;
 mod script;
+mod search;
 mod setting;
+mod statistic;
+mod store;
 mod system;
 mod window;
 
 //================================================================
 
-use crate::app::*;
+use crate::{app::*, argument::*, control::*, instance::*};
+use clap::Parser;
 use eframe::egui;
 
 //================================================================
 
 fn main() -> eframe::Result {
+    let argument = Argument::parse();
+
+    if argument.desktop {
+        print!("{}", Argument::make_desktop());
+        return Ok(());
+    }
+
+    // set before a control request too, as every configuration folder has its own control socket.
+    if let Some(path) = &argument.configuration {
+        App::set_configuration_path(path)
+            .map_err(|error| eframe::Error::AppCreation(error.into()))?;
+    }
+
+    if let Some(Command::Ctl { request, json }) = argument.command {
+        std::process::exit(Control::run(request, json));
+    }
+
+    // another instance is already running, and has been sent the argument.
+    let Some(instance) = Instance::new(&argument) else {
+        return Ok(());
+    };
+
+    if argument.headless {
+        return App::run_headless(argument, instance)
+            .map_err(|error| eframe::Error::AppCreation(error.into()));
+    }
+
     let i = eframe::icon_data::from_png_bytes(include_bytes!("../data/icon.png")).unwrap();
 
     // set window data.
@@ -80,7 +122,12 @@ fn main() -> eframe::Result {
         configuration,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(App::new(cc)?))
+            Ok(Box::new(App::new(
+                &cc.egui_ctx,
+                Some(cc),
+                argument,
+                instance,
+            )?))
         }),
     )
 }
@@ -0,0 +1,862 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{app::*, queue::*, setting::*, system::*};
+
+//================================================================
+
+use eframe::egui;
+use souvlaki::MediaControlEvent;
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    ops::Range,
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender},
+    },
+    time::Duration,
+};
+
+//================================================================
+
+/// MPD command, as every argument, with a sender for the response. An error is an MPD error code and message.
+pub type Query = (Vec<String>, Sender<Result<String, (u32, String)>>);
+
+/// MPD connection input, either a line from the client, or a play state change for idle.
+enum Input {
+    Line(String),
+    /// a changed MPD sub-system, i.e. "player".
+    Change(&'static str),
+    Close,
+}
+
+/// every MPD connection input sender, for a play state change.
+type ChangeList = Arc<Mutex<Vec<Sender<Input>>>>;
+
+/// search filter, as a lower-case tag, a value, whether the match is exact, and whether to negate the match.
+type Filter = (String, String, bool, bool);
+
+/// MPD protocol server. Every song ID is the song's queue position.
+pub struct Mpd {
+    list_change: ChangeList,
+    /// last published play state hash, for every sub-system in Mpd::LIST_SYSTEM.
+    last: RefCell<Option<[u64; 4]>>,
+}
+
+impl Mpd {
+    const VERSION: &str = "0.23.0";
+    /// maximum time to wait for the app to answer a command.
+    const TIME_OUT: Duration = Duration::from_secs(5);
+    const LIST_SYSTEM: [&str; 4] = ["player", "mixer", "playlist", "options"];
+    const LIST_TAG: [&str; 6] = ["Artist", "Album", "Title", "Genre", "Date", "Track"];
+    const ERROR_ARG: u32 = 2;
+    const ERROR_PASSWORD: u32 = 3;
+    const ERROR_UNKNOWN: u32 = 5;
+    const ERROR_NO_EXIST: u32 = 50;
+    const ERROR_SYSTEM: u32 = 52;
+    const LIST_COMMAND: [&str; 46] = [
+        "add",
+        "addid",
+        "clear",
+        "close",
+        "commands",
+        "command_list_begin",
+        "command_list_end",
+        "command_list_ok_begin",
+        "consume",
+        "currentsong",
+        "decoders",
+        "delete",
+        "deleteid",
+        "find",
+        "findadd",
+        "getvol",
+        "idle",
+        "kill",
+        "list",
+        "move",
+        "next",
+        "noidle",
+        "notcommands",
+        "outputs",
+        "password",
+        "pause",
+        "ping",
+        "play",
+        "playid",
+        "playlistid",
+        "playlistinfo",
+        "plchanges",
+        "plchangesposid",
+        "previous",
+        "random",
+        "repeat",
+        "search",
+        "searchadd",
+        "seek",
+        "seekcur",
+        "seekid",
+        "setvol",
+        "single",
+        "status",
+        "stop",
+        "tagtypes",
+    ];
+
+    /// Listen for every MPD connection, on a separate thread. Returns none if the MPD server is disabled.
+    pub fn new(
+        setting: &Setting,
+        context: egui::Context,
+    ) -> anyhow::Result<Option<(Self, Receiver<Query>)>> {
+        if !setting.mpd_allow {
+            return Ok(None);
+        }
+
+        let listener = TcpListener::bind(&setting.mpd_address)
+            .map_err(|error| anyhow::anyhow!("Couldn't start MPD server: {error}"))?;
+        let list_change = ChangeList::default();
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let mpd = Self {
+            list_change: list_change.clone(),
+            last: RefCell::new(None),
+        };
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                let context = context.clone();
+                let list_change = list_change.clone();
+
+                std::thread::spawn(move || Self::handle(stream, tx, context, list_change));
+            }
+        });
+
+        Ok(Some((mpd, rx)))
+    }
+
+    /// Notify every MPD connection of every changed sub-system, for idle.
+    pub fn publish(&self, app: &App) {
+        let status = Self::get_status(app);
+
+        let state = [
+            Self::get_hash((status, app.queue.state, app.queue.index)),
+            Self::get_hash(app.system.sink.volume().to_bits()),
+            Self::get_hash(&app.queue.list),
+            Self::get_hash((app.queue.repeat as u8, app.queue.random as u8)),
+        ];
+
+        let mut last = self.last.borrow_mut();
+
+        if let Some(last) = last.as_ref() {
+            let mut list_change = self.list_change.lock().unwrap();
+
+            for (i, system) in Self::LIST_SYSTEM.iter().enumerate() {
+                if last[i] != state[i] {
+                    // drop every closed connection.
+                    list_change.retain(|x| x.send(Input::Change(system)).is_ok());
+                }
+            }
+        }
+
+        *last = Some(state);
+    }
+
+    /// Answer an MPD command.
+    #[rustfmt::skip]
+    pub fn make_query(
+        argument: Vec<String>,
+        app: &mut App,
+        context: &egui::Context,
+    ) -> Result<String, (u32, String)> {
+        let name = argument.first().map(String::as_str).unwrap_or_default();
+        let value = |i: usize| argument.get(i).map(String::as_str).ok_or((Self::ERROR_ARG, "missing argument".to_string()));
+        let number = |i: usize| value(i)?.parse::<usize>().map_err(|_| (Self::ERROR_ARG, format!("invalid number \"{}\"", argument[i])));
+        let system = |result: anyhow::Result<()>| result.map_err(|error| (Self::ERROR_SYSTEM, error.to_string()));
+        let length = app.queue.list.len();
+
+        match name {
+            "ping" | "consume" | "notcommands" | "decoders" => {}
+            "password" => return Err((Self::ERROR_PASSWORD, "password is not supported".to_string())),
+            "tagtypes" => {
+                return Ok(Self::LIST_TAG.iter().map(|x| format!("tagtype: {x}\n")).collect());
+            }
+            "commands" => {
+                return Ok(Self::LIST_COMMAND.iter().map(|x| format!("command: {x}\n")).collect());
+            }
+            "outputs" => return Ok("outputid: 0\noutputname: Melodix\noutputenabled: 1\n".to_string()),
+            "status" => return Ok(Self::get_status_text(app)),
+            "currentsong" => {
+                if let Some(entry) = app.queue.state {
+                    return Ok(Self::get_song(app, entry, Some(app.queue.index)));
+                }
+            }
+            "play" | "playid" => {
+                let index = match argument.get(1) {
+                    Some(_) if name == "playid" => Self::get_index(app, number(1)?)?,
+                    Some(_) => number(1)?,
+                    None if app.queue.state.is_some() => {
+                        app.track_play();
+                        return Ok(String::default());
+                    }
+                    None => app.queue.index,
+                };
+
+                Self::play(app, index, context)?;
+            }
+            "pause" => match argument.get(1).map(String::as_str) {
+                Some("1") => app.track_pause(),
+                Some("0") => app.track_play(),
+                _         => app.track_toggle(),
+            },
+            "stop"     => system(System::make_event(Event::Media(MediaControlEvent::Stop),     app, context))?,
+            "kill"     => system(System::make_event(Event::Media(MediaControlEvent::Quit),     app, context))?,
+            "next"     => system(System::make_event(Event::Skip(true),                         app, context))?,
+            "previous" => system(System::make_event(Event::Skip(false),                        app, context))?,
+            "seek" | "seekid" => {
+                let index = match name {
+                    "seekid" => Self::get_index(app, number(1)?)?,
+                    _        => number(1)?,
+                };
+                let time = value(2)?.parse::<f64>().map_err(|_| (Self::ERROR_ARG, "invalid time".to_string()))?;
+
+                if app.queue.state.is_none() || index != app.queue.index {
+                    Self::play(app, index, context)?;
+                }
+
+                app.track_seek(time as i64, false);
+            }
+            "seekcur" => {
+                let text = value(1)?;
+                let time = text.parse::<f64>().map_err(|_| (Self::ERROR_ARG, "invalid time".to_string()))?;
+
+                // a signed time is relative to the current position.
+                app.track_seek(time as i64, text.starts_with(['+', '-']));
+            }
+            "setvol" => app.track_set_volume(number(1)?.min(100) as f32 / 100.0),
+            "getvol" => return Ok(format!("volume: {}\n", Self::get_volume(app))),
+            "repeat" | "single" => {
+                let flag = value(1)? == "1";
+
+                // MPD has a repeat flag and a single flag, while there is only one repeat mode.
+                app.queue.repeat = match (name, flag, app.queue.repeat) {
+                    ("repeat", true,  Repeat::Off) => Repeat::All,
+                    ("repeat", false, _)           => Repeat::Off,
+                    ("single", true,  _)           => Repeat::One,
+                    ("single", false, Repeat::One) => Repeat::All,
+                    (_, _, repeat)                 => repeat,
+                };
+            }
+            "random" => {
+                // keep the album random mode, if already set.
+                let random = match (value(1)? == "1", app.queue.random) {
+                    (true, Random::Off) => Random::Track,
+                    (true, random)      => random,
+                    (false, _)          => Random::Off,
+                };
+                app.queue.set_random(random);
+            }
+            "add" | "addid" => {
+                // only a file or folder in the library, as any other path would be read from the file system.
+                let list = Self::find_uri(app, value(1)?);
+
+                if list.is_empty() {
+                    return Err((Self::ERROR_NO_EXIST, "No such song".to_string()));
+                }
+
+                system(Queue::add(app, list, false, context))?;
+
+                // every entry is added at the end.
+                if name == "addid"
+                    && let Some(id) = app.queue.get_id(length)
+                {
+                    return Ok(format!("Id: {id}\n"));
+                }
+            }
+            "clear" => Queue::reset(app),
+            "delete" | "deleteid" => {
+                let list: Vec<usize> = match name {
+                    "deleteid" => vec![Self::get_index(app, number(1)?)?],
+                    _          => Self::get_range(Some(value(1)?), length)?.collect(),
+                };
+                system(Queue::detach(app, &list, context))?;
+            }
+            "move" => {
+                let from = number(1)?;
+                let to = number(2)?;
+
+                if from >= length || to >= length {
+                    return Err((Self::ERROR_ARG, "Bad song index".to_string()));
+                }
+
+                app.queue.shift(from, to);
+            }
+            "playlistinfo" | "playlistid" | "plchanges" => {
+                // every change is the whole queue, as there is no queue version history.
+                let range = match (name, argument.get(1)) {
+                    ("plchanges", _)        => 0..length,
+                    ("playlistid", Some(_)) => Self::get_index(app, number(1)?).map(|x| x..x + 1)?,
+                    _                       => Self::get_range(argument.get(1).map(String::as_str), length)?,
+                };
+
+                return Ok(range.map(|i| Self::get_song(app, app.queue.list[i], Some(i))).collect());
+            }
+            "plchangesposid" => {
+                return Ok((0..length).map(|i| format!("cpos: {i}\nId: {}\n", app.queue.get_id(i).unwrap_or_default())).collect());
+            }
+            "list" => {
+                let tag = value(1)?.to_lowercase();
+                let filter = Self::get_filter(&argument[2..], true)?;
+                let mut list: Vec<String> = Vec::new();
+
+                for entry in Self::find(app, &filter) {
+                    for text in Self::get_tag(app, entry, &tag) {
+                        if !list.contains(&text) {
+                            list.push(text);
+                        }
+                    }
+                }
+
+                let label = Self::get_tag_label(&tag);
+
+                return Ok(list.iter().map(|x| format!("{label}: {x}\n")).collect());
+            }
+            "find" | "search" | "findadd" | "searchadd" => {
+                let filter = Self::get_filter(&argument[1..], name.starts_with("find"))?;
+
+                if filter.is_empty() {
+                    return Err((Self::ERROR_ARG, "too few arguments".to_string()));
+                }
+                let list = Self::find(app, &filter);
+
+                if name.ends_with("add") {
+                    system(Queue::add(app, list, false, context))?;
+                } else {
+                    return Ok(list.into_iter().map(|x| Self::get_song(app, x, None)).collect());
+                }
+            }
+            _ => return Err((Self::ERROR_UNKNOWN, format!("unknown command \"{name}\""))),
+        }
+
+        Ok(String::default())
+    }
+
+    /// Get the queue position of a queue entry, by id.
+    fn get_index(app: &App, id: usize) -> Result<usize, (u32, String)> {
+        app.queue
+            .get_index(id)
+            .ok_or((Self::ERROR_NO_EXIST, "No such song".to_string()))
+    }
+
+    /// Play a queue entry, by queue position.
+    fn play(app: &mut App, index: usize, context: &egui::Context) -> Result<(), (u32, String)> {
+        let Some(track) = app.queue.list.get(index).copied() else {
+            return Err((Self::ERROR_ARG, "Bad song index".to_string()));
+        };
+
+        app.queue.jump(index);
+        app.track_add(track, context)
+            .map_err(|error| (Self::ERROR_SYSTEM, error.to_string()))
+    }
+
+    //================================================================
+
+    /// Answer every command from an MPD client, until the client disconnects.
+    fn handle(
+        stream: TcpStream,
+        tx: Sender<Query>,
+        context: egui::Context,
+        list_change: ChangeList,
+    ) {
+        let (input_tx, input_rx) = std::sync::mpsc::channel();
+
+        list_change.lock().unwrap().push(input_tx.clone());
+
+        let Ok(reader) = stream.try_clone() else {
+            return;
+        };
+
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                if input_tx.send(Input::Line(line)).is_err() {
+                    return;
+                }
+            }
+
+            let _ = input_tx.send(Input::Close);
+        });
+
+        let mut writer = stream;
+        // every changed sub-system since the last idle.
+        let mut change: HashSet<&str> = HashSet::new();
+        // idle sub-system filter, if idle. an empty filter is every sub-system.
+        let mut idle: Option<Vec<String>> = None;
+        // command list, and whether to answer every command with "list_OK".
+        let mut command: Option<(Vec<Vec<String>>, bool)> = None;
+
+        if writeln!(writer, "OK MPD {}", Self::VERSION).is_err() {
+            return;
+        }
+
+        while let Ok(input) = input_rx.recv() {
+            let mut text = String::new();
+
+            match input {
+                Input::Close => return,
+                Input::Change(system) => {
+                    change.insert(system);
+                }
+                Input::Line(line) => {
+                    let argument = Self::get_argument(&line);
+                    let name = argument.first().map(String::as_str).unwrap_or_default();
+
+                    if idle.is_some() {
+                        // any command other than "noidle" while idle is a protocol error.
+                        if name != "noidle" {
+                            return;
+                        }
+
+                        idle = None;
+                        text.push_str("OK\n");
+                    } else if let Some((list, list_ok)) = command.as_mut() {
+                        if name == "command_list_end" {
+                            text = Self::call_list(&tx, &context, list, *list_ok);
+                            command = None;
+                        } else {
+                            list.push(argument);
+                        }
+                    } else {
+                        match name {
+                            "idle" => idle = Some(argument[1..].to_vec()),
+                            "noidle" => text.push_str("OK\n"),
+                            "close" => return,
+                            "command_list_begin" => command = Some((Vec::new(), false)),
+                            "command_list_ok_begin" => command = Some((Vec::new(), true)),
+                            _ => text = Self::call_list(&tx, &context, &[argument], false),
+                        }
+                    }
+                }
+            }
+
+            // answer an idle as soon as there is a change to a sub-system of interest.
+            if let Some(filter) = idle.as_ref() {
+                let list: Vec<&str> = change
+                    .iter()
+                    .filter(|x| filter.is_empty() || filter.iter().any(|y| y == *x))
+                    .copied()
+                    .collect();
+
+                if !list.is_empty() {
+                    for system in list {
+                        change.remove(system);
+                        text.push_str(&format!("changed: {system}\n"));
+                    }
+
+                    text.push_str("OK\n");
+                    idle = None;
+                }
+            }
+
+            if !text.is_empty() && writer.write_all(text.as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Run a list of commands through the app, and get the response. Will stop at the first error.
+    fn call_list(
+        tx: &Sender<Query>,
+        context: &egui::Context,
+        list: &[Vec<String>],
+        list_ok: bool,
+    ) -> String {
+        let mut text = String::new();
+
+        for (i, argument) in list.iter().enumerate() {
+            let (response_tx, response_rx) = std::sync::mpsc::channel();
+            let name = argument.first().cloned().unwrap_or_default();
+
+            let response = if tx.send((argument.clone(), response_tx)).is_ok() {
+                context.request_repaint();
+                response_rx
+                    .recv_timeout(Self::TIME_OUT)
+                    .unwrap_or(Err((Self::ERROR_SYSTEM, "timed out".to_string())))
+            } else {
+                Err((Self::ERROR_SYSTEM, "server closed".to_string()))
+            };
+
+            match response {
+                Ok(response) => {
+                    text.push_str(&response);
+
+                    if list_ok {
+                        text.push_str("list_OK\n");
+                    }
+                }
+                Err((code, error)) => {
+                    text.push_str(&format!("ACK [{code}@{i}] {{{name}}} {error}\n"));
+                    return text;
+                }
+            }
+        }
+
+        text.push_str("OK\n");
+        text
+    }
+
+    /// Split a command line into every argument. An argument may be quoted, with a backslash escape.
+    fn get_argument(line: &str) -> Vec<String> {
+        let mut list = Vec::new();
+        let mut data = line.chars().peekable();
+
+        loop {
+            while data.next_if(|x| x.is_whitespace()).is_some() {}
+
+            let Some(first) = data.peek().copied() else {
+                return list;
+            };
+
+            let mut text = String::new();
+
+            if first == '"' {
+                data.next();
+
+                while let Some(character) = data.next() {
+                    match character {
+                        '\\' => text.extend(data.next()),
+                        '"' => break,
+                        character => text.push(character),
+                    }
+                }
+            } else {
+                while let Some(character) = data.next_if(|x| !x.is_whitespace()) {
+                    text.push(character);
+                }
+            }
+
+            list.push(text);
+        }
+    }
+
+    /// Get a queue position range, either as "START:END", "START:" or a single position. Will use the whole queue if there is no range.
+    fn get_range(text: Option<&str>, length: usize) -> Result<Range<usize>, (u32, String)> {
+        let error = || (Self::ERROR_ARG, "Bad song index".to_string());
+
+        let Some(text) = text else {
+            return Ok(0..length);
+        };
+
+        let range = match text.split_once(':') {
+            Some((a, b)) => {
+                let a = a.parse::<usize>().map_err(|_| error())?;
+                let b = if b.is_empty() {
+                    length
+                } else {
+                    b.parse::<usize>().map_err(|_| error())?
+                };
+                a..b
+            }
+            None => {
+                let a = text.parse::<usize>().map_err(|_| error())?;
+                a..a + 1
+            }
+        };
+
+        if range.start > range.end || range.end > length {
+            return Err(error());
+        }
+
+        Ok(range)
+    }
+
+    //================================================================
+
+    /// Get a search filter, as a tag, value, and whether to negate the match. Either as every tag and value pair, or as a filter expression (i.e. "((artist == 'A') AND (album != 'B'))").
+    fn get_filter(list: &[String], exact: bool) -> Result<Vec<Filter>, (u32, String)> {
+        let mut filter = Vec::new();
+        let error = || (Self::ERROR_ARG, "invalid filter".to_string());
+
+        let Some(first) = list.first() else {
+            return Ok(filter);
+        };
+
+        if first.starts_with('(') {
+            for clause in first.split(" AND ") {
+                let clause = clause.trim().trim_start_matches('(').trim_end_matches(')');
+                let (tag, rest) = clause.split_once(' ').ok_or_else(error)?;
+                let (operator, value) = rest.trim().split_once(' ').ok_or_else(error)?;
+                let value = value.trim();
+                let value = value
+                    .strip_prefix(['\'', '"'])
+                    .and_then(|x| x.strip_suffix(['\'', '"']))
+                    .unwrap_or(value)
+                    .replace("\\'", "'")
+                    .replace("\\\"", "\"");
+
+                let (exact, negate) = match operator {
+                    "==" => (true, false),
+                    "!=" => (true, true),
+                    "contains" => (false, false),
+                    _ => return Err(error()),
+                };
+
+                filter.push((tag.to_lowercase(), value, exact, negate));
+            }
+        } else {
+            for pair in list.chunks(2) {
+                // "group" and "sort" are only a presentation hint.
+                if matches!(pair[0].as_str(), "group" | "sort" | "window") {
+                    break;
+                }
+
+                let value = pair.get(1).ok_or_else(error)?;
+                filter.push((pair[0].to_lowercase(), value.clone(), exact, false));
+            }
+        }
+
+        Ok(filter)
+    }
+
+    /// Find every track matching a search filter, in library order.
+    fn find(app: &App, filter: &[Filter]) -> Vec<(usize, usize, usize)> {
+        let mut list = Vec::new();
+
+        for (i_group, group) in app.library.list_group.iter().enumerate() {
+            for (i_album, album) in group.list_album.iter().enumerate() {
+                for i_track in 0..album.list_track.len() {
+                    let entry = (i_group, i_album, i_track);
+
+                    let check = filter.iter().all(|(tag, value, exact, negate)| {
+                        let value = value.to_lowercase();
+                        let check = Self::get_tag(app, entry, tag).iter().any(|x| {
+                            if *exact {
+                                x.to_lowercase() == value
+                            } else {
+                                x.to_lowercase().contains(&value)
+                            }
+                        });
+
+                        check != *negate
+                    });
+
+                    if check {
+                        list.push(entry);
+                    }
+                }
+            }
+        }
+
+        list
+    }
+
+    /// Find every track by URI, i.e. a track key, or a folder containing any track.
+    fn find_uri(app: &mut App, uri: &str) -> Vec<(usize, usize, usize)> {
+        if let Some(entry) = app.library.find_entry(uri) {
+            return vec![entry];
+        }
+
+        let uri = uri.trim_end_matches('/');
+        let mut list = Vec::new();
+
+        for (i_group, group) in app.library.list_group.iter().enumerate() {
+            for (i_album, album) in group.list_album.iter().enumerate() {
+                for (i_track, track) in album.list_track.iter().enumerate() {
+                    if track
+                        .path
+                        .strip_prefix(uri)
+                        .is_some_and(|x| x.starts_with('/') || uri.is_empty())
+                    {
+                        list.push((i_group, i_album, i_track));
+                    }
+                }
+            }
+        }
+
+        list.sort_by(|a, b| app.library.compare_path(*a, *b));
+        list
+    }
+
+    /// Get every value of a tag for a track.
+    fn get_tag(app: &App, entry: (usize, usize, usize), tag: &str) -> Vec<String> {
+        let (group, album, track) = app.get_state(entry);
+
+        match tag {
+            "artist" | "albumartist" => vec![group.name.clone()],
+            "album" => vec![album.name.clone()],
+            "title" => vec![track.name.clone()],
+            "genre" => track
+                .get_genre()
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect(),
+            "date" => track.date.clone().into_iter().collect(),
+            "track" => track.track.map(|x| x.to_string()).into_iter().collect(),
+            "file" => vec![track.get_key()],
+            "any" => ["artist", "album", "title", "genre", "date", "file"]
+                .iter()
+                .flat_map(|x| Self::get_tag(app, entry, x))
+                .collect(),
+            _ => Vec::default(),
+        }
+    }
+
+    fn get_tag_label(tag: &str) -> &'static str {
+        match tag {
+            "albumartist" => "AlbumArtist",
+            "file" => "file",
+            _ => Self::LIST_TAG
+                .iter()
+                .find(|x| x.eq_ignore_ascii_case(tag))
+                .copied()
+                .unwrap_or("Artist"),
+        }
+    }
+
+    //================================================================
+
+    fn get_song(app: &App, entry: (usize, usize, usize), position: Option<usize>) -> String {
+        let (group, album, track) = app.get_state(entry);
+        let time = track.time.as_secs_f64();
+
+        let mut text = format!(
+            "file: {}\nArtist: {}\nAlbum: {}\nTitle: {}\n",
+            track.get_key(),
+            group.name,
+            album.name,
+            track.name
+        );
+
+        if let Some(number) = track.track {
+            text.push_str(&format!("Track: {number}\n"));
+        }
+
+        if let Some(date) = &track.date {
+            text.push_str(&format!("Date: {date}\n"));
+        }
+
+        for (_, genre) in track.get_genre() {
+            text.push_str(&format!("Genre: {genre}\n"));
+        }
+
+        text.push_str(&format!(
+            "Time: {}\nduration: {time:.3}\n",
+            time.round() as u64
+        ));
+
+        if let Some(position) = position
+            && let Some(id) = app.queue.get_id(position)
+        {
+            text.push_str(&format!("Pos: {position}\nId: {id}\n"));
+        }
+
+        text
+    }
+
+    fn get_status(app: &App) -> &'static str {
+        if app.queue.state.is_none() || app.system.sink.empty() {
+            "stop"
+        } else if app.system.sink.is_paused() {
+            "pause"
+        } else {
+            "play"
+        }
+    }
+
+    #[rustfmt::skip]
+    fn get_status_text(app: &App) -> String {
+        let status = Self::get_status(app);
+        let repeat = app.queue.repeat != Repeat::Off;
+        let single = app.queue.repeat == Repeat::One;
+        let random = app.queue.random != Random::Off;
+
+        let mut text = format!(
+            "volume: {}\nrepeat: {}\nrandom: {}\nsingle: {}\nconsume: 0\nplaylist: {}\nplaylistlength: {}\nstate: {status}\n",
+            Self::get_volume(app),
+            repeat as u8,
+            random as u8,
+            single as u8,
+            Self::get_hash(&app.queue.list) as u32,
+            app.queue.list.len(),
+        );
+
+        if let Some((_, _, track)) = app.get_play_state() {
+            let index = app.queue.index;
+            let elapsed = app.system.sink.get_pos().as_secs_f64();
+            let duration = track.time.as_secs_f64();
+
+            let id = app.queue.get_id(index).unwrap_or_default();
+
+            text.push_str(&format!("song: {index}\nsongid: {id}\n"));
+            text.push_str(&format!("time: {}:{}\nelapsed: {elapsed:.3}\nduration: {duration:.3}\n", elapsed as u64, duration as u64));
+        }
+
+        if let Some(index) = app.queue.peek()
+            && let Some(id) = app.queue.get_id(index)
+        {
+            text.push_str(&format!("nextsong: {index}\nnextsongid: {id}\n"));
+        }
+
+        text
+    }
+
+    fn get_volume(app: &App) -> u32 {
+        (app.system.sink.volume() * 100.0).round() as u32
+    }
+
+    fn get_hash(value: impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
@@ -0,0 +1,336 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{cue::*, library::*, queue::*};
+
+//================================================================
+
+use eframe::egui;
+use rodio::{Sink, Source};
+use std::{
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, RecvTimeoutError, Sender},
+    },
+    time::{Duration, Instant},
+};
+
+//================================================================
+
+/// A play-back event, from the player thread, the media sink or the queue.
+pub enum PlayerEvent {
+    /// a track has begun play-back.
+    TrackStarted,
+    /// a track was played to the end, by play-back id. has the next track, if the player thread has started it on its own, by play-back id and library entry.
+    TrackEnded(usize, Option<(usize, (usize, usize, usize))>),
+    /// the play position of a track, by play-back id, sent every second while a track is playing.
+    PositionTick(usize, Duration),
+    /// the queue has been modified.
+    QueueChanged,
+}
+
+/// A track's media, as the file to play, and the part of it to play.
+pub struct Media {
+    path: String,
+    /// start of the part to play, for a CUE sheet track.
+    offset: Option<Duration>,
+    /// length of the part to play, for a CUE sheet track.
+    time: Duration,
+    /// play position to resume from, for a long-form track.
+    seek: Option<Duration>,
+}
+
+impl Media {
+    pub fn new(track: &Track, seek: Option<Duration>) -> Self {
+        Self {
+            path: track.path.clone(),
+            offset: track.offset,
+            time: track.time,
+            seek,
+        }
+    }
+
+    /// Open and decode the media file.
+    fn open(&self) -> anyhow::Result<Box<dyn Source + Send>> {
+        let file = rodio::Decoder::try_from(std::fs::File::open(&self.path)?)?;
+
+        if let Some(offset) = self.offset {
+            Ok(Box::new(CueSource::new(file, offset, self.time)?))
+        } else {
+            Ok(Box::new(file))
+        }
+    }
+}
+
+/// Play order for the player thread, as last handed over by the app.
+struct Plan {
+    /// play-back id of the track the play order follows from.
+    play: usize,
+    /// a copy of the queue (see Queue::get_copy).
+    queue: Queue,
+    /// media of every queue entry, in step with the queue.
+    list_media: Vec<Media>,
+}
+
+/// Play-back state, shared with the player thread.
+#[derive(Default)]
+struct Shared {
+    /// play-back id of the current track, counting up with every track started or stopped. an end-of-track call-back with any other id is from a track that was since replaced.
+    play: usize,
+    /// play order to follow once the current track ends.
+    plan: Option<Plan>,
+}
+
+/// Play-back event handler. A separate thread will keep track of the media sink, and will start every next track from a copy of the play order on its own, so that play-back doesn't depend on the app (i.e. on the window being drawn). The app will follow the player thread on its next step (see App::track_advance).
+pub struct Player {
+    event_tx: Sender<PlayerEvent>,
+    event_rx: Receiver<PlayerEvent>,
+    /// end-of-track call-back sender, to the player thread.
+    end_tx: Sender<usize>,
+    shared: Arc<Mutex<Shared>>,
+    /// play-back id of the track the app is following. see Shared::play.
+    play: usize,
+    /// play order version of the last play order handed over to the player thread.
+    version: Option<Version>,
+    context: egui::Context,
+}
+
+impl Player {
+    /// time between every position tick.
+    const TICK: Duration = Duration::from_secs(1);
+
+    pub fn new(sink: &Arc<Sink>, context: egui::Context) -> Self {
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let (end_tx, end_rx) = std::sync::mpsc::channel();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let sink = Arc::downgrade(sink);
+        let thread_event_tx = event_tx.clone();
+        let thread_end_tx = end_tx.clone();
+        let thread_shared = shared.clone();
+        let clone = context.clone();
+
+        std::thread::spawn(move || {
+            let mut tick = Instant::now() + Self::TICK;
+
+            loop {
+                let end = end_rx.recv_timeout(tick.saturating_duration_since(Instant::now()));
+
+                // the media sink is gone, the app is closing.
+                let Some(sink) = sink.upgrade() else {
+                    break;
+                };
+
+                let event = match end {
+                    Ok(play) => {
+                        let mut shared = thread_shared.lock().unwrap();
+
+                        // a call-back from a track that was since replaced or stopped.
+                        if play != shared.play {
+                            continue;
+                        }
+
+                        let next = Self::advance(&sink, &mut shared, &thread_end_tx);
+
+                        PlayerEvent::TrackEnded(play, next)
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        tick = Instant::now() + Self::TICK;
+
+                        if sink.empty() || sink.is_paused() {
+                            continue;
+                        }
+
+                        let play = thread_shared.lock().unwrap().play;
+
+                        PlayerEvent::PositionTick(play, sink.get_pos())
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                if thread_event_tx.send(event).is_err() {
+                    break;
+                }
+
+                clone.request_repaint();
+            }
+        });
+
+        Self {
+            event_tx,
+            event_rx,
+            end_tx,
+            shared,
+            play: 0,
+            version: None,
+            context,
+        }
+    }
+
+    /// Get a new event sender, i.e. for the queue.
+    pub fn get_sender(&self) -> Sender<PlayerEvent> {
+        self.event_tx.clone()
+    }
+
+    /// Send an event, and wake the app up to handle it.
+    pub fn send(&self, event: PlayerEvent) {
+        let _ = self.event_tx.send(event);
+        self.context.request_repaint();
+    }
+
+    /// Try reading the next pending event.
+    pub fn poll(&self) -> Option<PlayerEvent> {
+        self.event_rx.try_recv().ok()
+    }
+
+    /// Replace the current track with a new one, and begin play-back. Will discard the play order, until the app hands over a new one.
+    pub fn play(&mut self, sink: &Sink, media: &Media) -> anyhow::Result<()> {
+        let source = media.open()?;
+        let mut shared = self.shared.lock().unwrap();
+
+        shared.play += 1;
+        shared.plan = None;
+        self.play = shared.play;
+        self.version = None;
+
+        // kill the current track, add new track.
+        sink.stop();
+        Self::append(sink, source, shared.play, &self.end_tx);
+        sink.play();
+
+        // resume long-form track from the last known play position.
+        if let Some(time) = media.seek {
+            let _ = sink.try_seek(time);
+        }
+
+        Ok(())
+    }
+
+    /// Stop play-back, discarding the play order.
+    pub fn stop(&mut self, sink: &Sink) {
+        let mut shared = self.shared.lock().unwrap();
+
+        shared.play += 1;
+        shared.plan = None;
+        self.play = shared.play;
+        self.version = None;
+
+        sink.stop();
+    }
+
+    /// Check if a play-back id is the track the app is following.
+    pub fn is_current(&self, play: usize) -> bool {
+        self.play == play
+    }
+
+    /// Follow a track the player thread has started on its own.
+    pub fn follow(&mut self, play: usize) {
+        self.play = play;
+    }
+
+    /// Check if the player thread already has a play order of the given version.
+    pub fn is_planned(&self, version: &Version) -> bool {
+        self.version.as_ref() == Some(version)
+    }
+
+    /// Hand over a copy of the play order (see Queue::get_copy) to the player thread, with the media of every queue entry.
+    pub fn plan(&mut self, version: Version, queue: Queue, list_media: Vec<Media>) {
+        let mut shared = self.shared.lock().unwrap();
+
+        // the player thread has started a track the app has yet to follow, and its own play order is already ahead of the app's.
+        if shared.play != self.play {
+            return;
+        }
+
+        shared.plan = Some(Plan {
+            play: self.play,
+            queue,
+            list_media,
+        });
+        self.version = Some(version);
+    }
+
+    /// Start the next track from the play order, once the current track has ended. Returns the next track's play-back id and library entry, or none if play-back should stop, or if it's up to the app.
+    fn advance(
+        sink: &Sink,
+        shared: &mut Shared,
+        end_tx: &Sender<usize>,
+    ) -> Option<(usize, (usize, usize, usize))> {
+        let mut plan = shared.plan.take().filter(|x| x.play == shared.play)?;
+        let entry = plan.queue.advance()?;
+        let media = plan.list_media.get(plan.queue.index)?;
+        let source = media.open().ok()?;
+        let seek = media.seek;
+
+        shared.play += 1;
+        plan.play = shared.play;
+
+        Self::append(sink, source, shared.play, end_tx);
+
+        // resume long-form track from the last known play position.
+        if let Some(time) = seek {
+            let _ = sink.try_seek(time);
+        }
+
+        shared.plan = Some(plan);
+
+        Some((shared.play, entry))
+    }
+
+    /// Add a track to the media sink, with a call-back for when the track is over.
+    fn append(sink: &Sink, source: Box<dyn Source + Send>, play: usize, end_tx: &Sender<usize>) {
+        let end_tx = end_tx.clone();
+
+        sink.append(source);
+        sink.append(rodio::source::EmptyCallback::new(Box::new(move || {
+            let _ = end_tx.send(play);
+        })));
+    }
+}
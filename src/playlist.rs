@@ -0,0 +1,1085 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{library::*, statistic::*, store::*};
+
+//================================================================
+
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+//================================================================
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Playlist {
+    /// every playlist, in user order.
+    pub list_entry: Vec<PlaylistEntry>,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub name: String,
+    /// every track in the playlist, by track key (see Track::get_key), so that a playlist will survive a library re-scan.
+    pub list_track: Vec<String>,
+    /// smart playlist rule. if present, the track list is ignored and every track matching the rule is used instead.
+    pub rule: Option<Rule>,
+    /// smart playlist match list, for group, album, track. will be re-evaluated if none.
+    #[serde(skip)]
+    pub list_match: Option<Vec<(usize, usize, usize)>>,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    /// match a track if any condition is met, rather than every condition.
+    pub any: bool,
+    pub list_condition: Vec<Condition>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    /// genre contains the given text.
+    Genre(String),
+    /// year is within the given range, inclusive.
+    Year(u32, u32),
+    /// track is longer than the given minute count.
+    Time(u64),
+    /// track was played to the end at least the given number of times.
+    PlayCount(u64),
+    /// track was played to the end within the given day count.
+    PlayTime(u64),
+    /// track path starts with the given text.
+    Path(String),
+}
+
+/// playlist layout from before there was a layout version or a smart playlist rule, for migration.
+#[derive(Deserialize)]
+struct PlaylistLegacy {
+    list_entry: Vec<PlaylistEntryLegacy>,
+}
+
+#[derive(Deserialize)]
+struct PlaylistEntryLegacy {
+    name: String,
+    list_track: Vec<String>,
+}
+
+/// playlist file entry, with any metadata the playlist file has for it.
+#[derive(Default)]
+pub struct FileEntry {
+    pub path: String,
+    pub group: Option<String>,
+    pub album: Option<String>,
+    pub name: Option<String>,
+    /// entry title, as "group - name", for a playlist format without a separate group and name.
+    pub title: Option<String>,
+    /// track length, in seconds.
+    pub time: Option<u64>,
+    /// start and end time into the audio file, for a track from a CUE sheet.
+    pub offset: Option<(Duration, Duration)>,
+}
+
+impl Playlist {
+    /// every supported playlist file extension.
+    pub const FORMAT_LIST: [&str; 4] = ["m3u", "m3u8", "pls", "xspf"];
+
+    pub fn new() -> Self {
+        Self::load()
+    }
+
+    /// Create a new, empty playlist with a unique name. Returns the index of the new playlist.
+    pub fn create(&mut self) -> usize {
+        self.create_rule(None)
+    }
+
+    /// Create a new playlist with a unique name, which will be a smart playlist if given a rule. Returns the index of the new playlist.
+    pub fn create_rule(&mut self, rule: Option<Rule>) -> usize {
+        let mut count = self.list_entry.len() + 1;
+
+        while self
+            .list_entry
+            .iter()
+            .any(|x| x.name == format!("Playlist {count}"))
+        {
+            count += 1;
+        }
+
+        self.list_entry.push(PlaylistEntry {
+            name: format!("Playlist {count}"),
+            list_track: Vec::default(),
+            rule,
+            list_match: None,
+        });
+
+        self.list_entry.len() - 1
+    }
+
+    /// Add a list of track keys to a playlist. If the index is past the end of the playlist list, a new playlist will be created.
+    pub fn insert(&mut self, index: usize, list: Vec<String>) {
+        let index = if index < self.list_entry.len() {
+            index
+        } else {
+            self.create()
+        };
+
+        self.list_entry[index].list_track.extend(list);
+    }
+
+    /// Write a track list to a playlist file, in UTF-8. The format (M3U, PLS or XSPF) is selected by the file extension. If relative is set, every path will be relative to the playlist file's folder.
+    pub fn write(
+        path: &Path,
+        list: &[(&Group, &Album, &Track)],
+        relative: bool,
+    ) -> anyhow::Result<()> {
+        let folder = path.parent().unwrap_or(Path::new(""));
+        let list: Vec<FileEntry> = list
+            .iter()
+            .map(|(group, album, track)| {
+                // write the real audio file path, rather than the track key, so that any other player can open it.
+                let mut entry = PathBuf::from(&track.path);
+
+                if relative {
+                    entry = Self::get_relative_path(&entry, folder);
+                }
+
+                FileEntry {
+                    path: entry.display().to_string(),
+                    group: Some(group.name.clone()),
+                    album: Some(album.name.clone()),
+                    name: Some(track.name.clone()),
+                    title: None,
+                    time: Some(track.time.as_secs()),
+                    offset: track.offset.map(|x| (x, x + track.time)),
+                }
+            })
+            .collect();
+
+        let data = match Self::get_extension(path).as_str() {
+            "pls" => Self::write_pls(&list),
+            "xspf" => Self::write_xspf(&list),
+            _ => Self::write_m3u(&list),
+        };
+
+        std::fs::write(path, data)?;
+
+        Ok(())
+    }
+
+    /// Read every entry from a playlist file. The format (M3U, PLS or XSPF) is selected by the file extension. Relative entries will be resolved against the playlist file's folder.
+    pub fn read(path: &Path) -> anyhow::Result<Vec<FileEntry>> {
+        let folder = path.parent().unwrap_or(Path::new(""));
+        let data = std::fs::read(path)?;
+        let data = String::from_utf8_lossy(&data);
+        let data = data.trim_start_matches('\u{feff}');
+
+        let mut list = match Self::get_extension(path).as_str() {
+            "pls" => Self::read_pls(data),
+            "xspf" => Self::read_xspf(data),
+            _ => Self::read_m3u(data),
+        };
+
+        for entry in &mut list {
+            entry.path = Self::get_absolute_path(Path::new(&entry.path), folder)
+                .display()
+                .to_string();
+        }
+
+        Ok(list)
+    }
+
+    /// Check if a path is a playlist file, by file extension.
+    pub fn is_playlist(path: &Path) -> bool {
+        Self::FORMAT_LIST.contains(&Self::get_extension(path).as_str())
+    }
+
+    fn get_extension(path: &Path) -> String {
+        path.extension()
+            .map(|x| x.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    }
+
+    fn write_m3u(list: &[FileEntry]) -> String {
+        let mut data = String::from("#EXTM3U\n");
+
+        for entry in list {
+            data.push_str(&format!(
+                "#EXTINF:{},{}\n",
+                entry.time.unwrap_or_default(),
+                entry.get_title(),
+            ));
+
+            // VLC play-back option, for a CUE sheet track.
+            if let Some((start, end)) = entry.offset {
+                data.push_str(&format!(
+                    "#EXTVLCOPT:start-time={}\n#EXTVLCOPT:stop-time={}\n",
+                    start.as_secs_f64(),
+                    end.as_secs_f64()
+                ));
+            }
+
+            data.push_str(&format!("{}\n", entry.path));
+        }
+
+        data
+    }
+
+    fn read_m3u(data: &str) -> Vec<FileEntry> {
+        let mut list = Vec::new();
+        let mut title = None;
+        let mut start = None;
+        let mut end = None;
+
+        for line in data.lines().map(str::trim) {
+            if let Some(info) = line.strip_prefix("#EXTINF:") {
+                title = info
+                    .split_once(',')
+                    .map(|(time, name)| (time.trim().to_string(), name.trim().to_string()));
+            } else if let Some(option) = line.strip_prefix("#EXTVLCOPT:") {
+                if let Some(time) = option.strip_prefix("start-time=") {
+                    start = Self::parse_second(time);
+                } else if let Some(time) = option.strip_prefix("stop-time=") {
+                    end = Self::parse_second(time);
+                }
+            } else if !line.is_empty() && !line.starts_with('#') {
+                let mut entry = FileEntry::new(line);
+
+                // #EXTINF:time,group - name
+                if let Some((time, name)) = title.take() {
+                    entry.time = time.parse().ok();
+                    entry.title = Some(name).filter(|x| !x.is_empty());
+                }
+
+                if let Some(start) = start.take() {
+                    entry.offset = Some((start, end.take().unwrap_or(start)));
+                }
+
+                list.push(entry);
+            }
+        }
+
+        list
+    }
+
+    fn write_pls(list: &[FileEntry]) -> String {
+        let mut data = String::from("[playlist]\n");
+
+        for (i, entry) in list.iter().enumerate() {
+            let i = i + 1;
+
+            data.push_str(&format!("File{i}={}\n", entry.path));
+            data.push_str(&format!("Title{i}={}\n", entry.get_title()));
+            data.push_str(&format!("Length{i}={}\n", entry.time.unwrap_or_default()));
+        }
+
+        data.push_str(&format!("NumberOfEntries={}\nVersion=2\n", list.len()));
+
+        data
+    }
+
+    fn read_pls(data: &str) -> Vec<FileEntry> {
+        let mut list: Vec<(usize, FileEntry)> = Vec::new();
+        let mut title: Vec<(usize, String)> = Vec::new();
+        let mut time: Vec<(usize, u64)> = Vec::new();
+
+        for line in data.lines().map(str::trim) {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            if let Some(i) = key.strip_prefix("file").and_then(|x| x.parse().ok()) {
+                list.push((i, FileEntry::new(value)));
+            } else if let Some(i) = key.strip_prefix("title").and_then(|x| x.parse().ok()) {
+                title.push((i, value.to_string()));
+            } else if let Some(i) = key.strip_prefix("length").and_then(|x| x.parse().ok())
+                && let Ok(value) = value.parse()
+            {
+                time.push((i, value));
+            }
+        }
+
+        list.sort_by_key(|(i, _)| *i);
+
+        list.into_iter()
+            .map(|(i, mut entry)| {
+                entry.title = title
+                    .iter()
+                    .find(|(x, name)| *x == i && !name.is_empty())
+                    .map(|(_, x)| x.clone());
+                entry.time = time.iter().find(|(x, _)| *x == i).map(|(_, x)| *x);
+                entry
+            })
+            .collect()
+    }
+
+    fn write_xspf(list: &[FileEntry]) -> String {
+        let mut data = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\" xmlns:vlc=\"http://www.videolan.org/vlc/playlist/ns/0/\">\n  <trackList>\n",
+        );
+
+        for entry in list {
+            data.push_str("    <track>\n");
+            data.push_str(&format!(
+                "      <location>{}</location>\n",
+                Self::xml_escape(&Self::url_encode(&entry.path))
+            ));
+
+            if let Some(name) = &entry.name {
+                data.push_str(&format!(
+                    "      <title>{}</title>\n",
+                    Self::xml_escape(name)
+                ));
+            }
+            if let Some(group) = &entry.group {
+                data.push_str(&format!(
+                    "      <creator>{}</creator>\n",
+                    Self::xml_escape(group)
+                ));
+            }
+            if let Some(album) = &entry.album {
+                data.push_str(&format!(
+                    "      <album>{}</album>\n",
+                    Self::xml_escape(album)
+                ));
+            }
+            if let Some(time) = entry.time {
+                data.push_str(&format!("      <duration>{}</duration>\n", time * 1000));
+            }
+            // VLC play-back option, for a CUE sheet track.
+            if let Some((start, end)) = entry.offset {
+                data.push_str(&format!(
+                    "      <extension application=\"http://www.videolan.org/vlc/playlist/0\">\n        <vlc:option>start-time={}</vlc:option>\n        <vlc:option>stop-time={}</vlc:option>\n      </extension>\n",
+                    start.as_secs_f64(),
+                    end.as_secs_f64()
+                ));
+            }
+
+            data.push_str("    </track>\n");
+        }
+
+        data.push_str("  </trackList>\n</playlist>\n");
+
+        data
+    }
+
+    fn read_xspf(data: &str) -> Vec<FileEntry> {
+        let mut list = Vec::new();
+
+        for block in Self::xml_list(data, "track") {
+            let Some(location) = Self::xml_get(block, "location") else {
+                continue;
+            };
+
+            let mut entry = FileEntry::new(&Self::url_decode(&location));
+
+            entry.name = Self::xml_get(block, "title");
+            entry.group = Self::xml_get(block, "creator");
+            entry.album = Self::xml_get(block, "album");
+            entry.time = Self::xml_get(block, "duration")
+                .and_then(|x| x.parse::<u64>().ok())
+                .map(|x| x / 1000);
+
+            let option: Vec<String> = Self::xml_list(block, "vlc:option")
+                .into_iter()
+                .map(Self::xml_text)
+                .collect();
+            let start = option
+                .iter()
+                .find_map(|x| x.strip_prefix("start-time=").and_then(Self::parse_second));
+            let end = option
+                .iter()
+                .find_map(|x| x.strip_prefix("stop-time=").and_then(Self::parse_second));
+
+            entry.offset = start.map(|start| (start, end.unwrap_or(start)));
+
+            list.push(entry);
+        }
+
+        list
+    }
+
+    /// Parse a time in seconds, i.e. "243.36", to the nearest milli-second, as in a CUE sheet time-stamp.
+    fn parse_second(text: &str) -> Option<Duration> {
+        let time = text.trim().parse::<f64>().ok()?;
+
+        if !time.is_finite() || time < 0.0 {
+            return None;
+        }
+
+        Some(Duration::from_millis((time * 1000.0).round() as u64))
+    }
+
+    /// Get the text of the first element with the given tag name, un-escaped.
+    fn xml_get(block: &str, tag: &str) -> Option<String> {
+        Self::xml_list(block, tag)
+            .first()
+            .map(|x| Self::xml_text(x))
+    }
+
+    /// Get the content of every element with the given tag name, in document order. An element may have attributes, or be empty (i.e. "<track/>").
+    fn xml_list<'a>(data: &'a str, tag: &str) -> Vec<&'a str> {
+        let open = format!("<{tag}");
+        let close = format!("</{tag}>");
+        let mut list = Vec::new();
+        let mut data = data;
+
+        while let Some(i) = data.find(&open) {
+            let text = &data[i + open.len()..];
+
+            let Some(end) = text.find('>') else {
+                break;
+            };
+
+            let head = &text[..end];
+            let text = &text[end + 1..];
+
+            // a different tag with the same prefix, i.e. "<trackList>" for "track".
+            if !head.is_empty() && !head.starts_with(char::is_whitespace) && head != "/" {
+                data = text;
+                continue;
+            }
+
+            if head.ends_with('/') {
+                list.push("");
+                data = text;
+                continue;
+            }
+
+            let Some(end) = text.find(&close) else {
+                break;
+            };
+
+            list.push(&text[..end]);
+            data = &text[end + close.len()..];
+        }
+
+        list
+    }
+
+    /// Get the text of an element, un-escaped. A CDATA section is kept verbatim.
+    fn xml_text(text: &str) -> String {
+        let mut data = String::new();
+        let mut text = text.trim();
+
+        while let Some(i) = text.find("<![CDATA[") {
+            data.push_str(&Self::xml_unescape(&text[..i]));
+
+            let (block, rest) = text[i + 9..]
+                .split_once("]]>")
+                .unwrap_or((&text[i + 9..], ""));
+
+            data.push_str(block);
+            text = rest;
+        }
+
+        data.push_str(&Self::xml_unescape(text));
+
+        data
+    }
+
+    /// Un-escape every named and numeric (i.e. "&#38;" or "&#x26;") character reference.
+    fn xml_unescape(text: &str) -> String {
+        let number = |name: &str| -> Option<char> {
+            let code = match name.strip_prefix(['x', 'X']) {
+                Some(code) => u32::from_str_radix(code, 16).ok()?,
+                None => name.parse().ok()?,
+            };
+
+            char::from_u32(code)
+        };
+
+        let mut data = String::new();
+        let mut text = text;
+
+        while let Some(i) = text.find('&') {
+            data.push_str(&text[..i]);
+            text = &text[i..];
+
+            let entity = text.find(';').and_then(|end| {
+                let code = match &text[1..end] {
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "amp" => Some('&'),
+                    name => name.strip_prefix('#').and_then(number),
+                };
+
+                code.map(|code| (code, end))
+            });
+
+            if let Some((code, end)) = entity {
+                data.push(code);
+                text = &text[end + 1..];
+            } else {
+                data.push('&');
+                text = &text[1..];
+            }
+        }
+
+        data.push_str(text);
+
+        data
+    }
+
+    fn xml_escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+
+    /// Get a file URL from a path. A relative path will stay as a relative URL.
+    pub fn url_encode(path: &str) -> String {
+        let path = path.replace('\\', "/");
+        let mut data = String::new();
+
+        if Path::new(&path).is_absolute() || path.chars().nth(1) == Some(':') {
+            data.push_str("file://");
+
+            // Windows drive path, i.e. file:///C:/...
+            if !path.starts_with('/') {
+                data.push('/');
+            }
+        }
+
+        for byte in path.bytes() {
+            match byte {
+                b'A'..=b'Z'
+                | b'a'..=b'z'
+                | b'0'..=b'9'
+                | b'-'
+                | b'_'
+                | b'.'
+                | b'~'
+                | b'/'
+                | b':' => data.push(byte as char),
+                _ => data.push_str(&format!("%{byte:02X}")),
+            }
+        }
+
+        data
+    }
+
+    /// Get a path from a file URL, or a relative URL.
+    fn url_decode(text: &str) -> String {
+        let text = text.strip_prefix("file://").unwrap_or(text);
+        let byte = text.as_bytes();
+        let mut data = Vec::new();
+        let mut i = 0;
+
+        while i < byte.len() {
+            if byte[i] == b'%'
+                && let Some(code) = text.get(i + 1..i + 3)
+                && let Ok(code) = u8::from_str_radix(code, 16)
+            {
+                data.push(code);
+                i += 3;
+            } else {
+                data.push(byte[i]);
+                i += 1;
+            }
+        }
+
+        let data = String::from_utf8_lossy(&data).to_string();
+
+        // Windows drive path, i.e. /C:/...
+        if data.starts_with('/') && data.get(2..3) == Some(":") {
+            return data[1..].to_string();
+        }
+
+        data
+    }
+
+    /// Get a path relative to a folder, walking up with ".." if necessary.
+    fn get_relative_path(path: &Path, folder: &Path) -> PathBuf {
+        let path_list: Vec<Component> = path.components().collect();
+        let folder_list: Vec<Component> = folder.components().collect();
+
+        // a path on a different root (i.e. a different drive) can't be relative.
+        if path_list.first() != folder_list.first() {
+            return path.to_path_buf();
+        }
+
+        let share = path_list
+            .iter()
+            .zip(folder_list.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut relative = PathBuf::new();
+
+        for _ in share..folder_list.len() {
+            relative.push("..");
+        }
+
+        for component in &path_list[share..] {
+            relative.push(component);
+        }
+
+        relative
+    }
+
+    /// Get the absolute path of a path relative to a folder, resolving every "." and "..".
+    fn get_absolute_path(path: &Path, folder: &Path) -> PathBuf {
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+
+        let mut absolute = PathBuf::new();
+
+        for component in folder.join(path).components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    absolute.pop();
+                }
+                component => absolute.push(component),
+            }
+        }
+
+        absolute
+    }
+
+    /// Discard every smart playlist match list, so that it will be re-evaluated. Use when the library or play statistic changes.
+    pub fn refresh(&mut self) {
+        for entry in &mut self.list_entry {
+            entry.list_match = None;
+        }
+    }
+}
+
+impl PlaylistEntry {
+    /// Get every track in the playlist that is in the library, as a queue entry.
+    pub fn get_entry(
+        &mut self,
+        library: &Library,
+        statistic: &Statistic,
+    ) -> Vec<(usize, usize, usize)> {
+        if let Some(rule) = &self.rule {
+            self.list_match
+                .get_or_insert_with(|| rule.get_entry(library, statistic))
+                .clone()
+        } else {
+            self.list_track
+                .iter()
+                .filter_map(|key| library.get_entry(key))
+                .collect()
+        }
+    }
+
+    /// Move a track to a different position in the playlist.
+    pub fn shift(&mut self, from: usize, to: usize) {
+        let track = self.list_track.remove(from);
+        self.list_track.insert(to, track);
+    }
+}
+
+impl Rule {
+    /// Get every track in the library that matches the rule, as a queue entry.
+    pub fn get_entry(
+        &self,
+        library: &Library,
+        statistic: &Statistic,
+    ) -> Vec<(usize, usize, usize)> {
+        // an empty rule matches nothing, rather than the whole library in "match every" mode.
+        if self.list_condition.is_empty() {
+            return Vec::new();
+        }
+
+        let time = Statistic::get_time();
+        let mut list = Vec::new();
+
+        for (i_group, group) in library.list_group.iter().enumerate() {
+            for (i_album, album) in group.list_album.iter().enumerate() {
+                for (i_track, track) in album.list_track.iter().enumerate() {
+                    let play = statistic.get(&track.get_key());
+                    let mut condition = self.list_condition.iter();
+
+                    let check = if self.any {
+                        condition.any(|x| x.check(track, play, time))
+                    } else {
+                        condition.all(|x| x.check(track, play, time))
+                    };
+
+                    if check {
+                        list.push((i_group, i_album, i_track));
+                    }
+                }
+            }
+        }
+
+        list
+    }
+}
+
+impl Condition {
+    /// every condition kind, with a default value.
+    pub fn get_list() -> [Self; 6] {
+        [
+            Self::Genre(String::default()),
+            Self::Year(1900, 2100),
+            Self::Time(20),
+            Self::PlayCount(1),
+            Self::PlayTime(30),
+            Self::Path(String::default()),
+        ]
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Genre(_) => "Genre contains",
+            Self::Year(_, _) => "Year between",
+            Self::Time(_) => "Longer than",
+            Self::PlayCount(_) => "Played at least",
+            Self::PlayTime(_) => "Played within the last",
+            Self::Path(_) => "Path starts with",
+        }
+    }
+
+    /// Check if a track meets the condition. The time is the current time, in seconds since the Unix epoch.
+    pub fn check(&self, track: &Track, play: Play, time: u64) -> bool {
+        match self {
+            Self::Genre(genre) => track
+                .kind
+                .as_ref()
+                .is_some_and(|kind| kind.to_lowercase().contains(&genre.to_lowercase())),
+            Self::Year(a, b) => track
+                .get_year()
+                .is_some_and(|year| year >= *a && year <= *b),
+            Self::Time(minute) => track.time.as_secs() > minute * 60,
+            Self::PlayCount(count) => play.count >= *count,
+            Self::PlayTime(day) => play.count > 0 && play.time + day * 86400 >= time,
+            Self::Path(path) => track.path.starts_with(path.as_str()),
+        }
+    }
+}
+
+impl FileEntry {
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Get the entry title, as "group - name".
+    fn get_title(&self) -> String {
+        if let Some(title) = &self.title {
+            return title.clone();
+        }
+
+        match (&self.group, &self.name) {
+            (Some(group), Some(name)) => format!("{group} - {name}"),
+            (None, Some(name)) => name.clone(),
+            _ => String::default(),
+        }
+    }
+
+    /// Get every group and name the entry may stand for. A "group - name" title is ambiguous if the group or the name has the separator in it, so every split is given, and then the whole title as a name.
+    fn get_name_list(&self) -> Vec<(Option<&str>, &str)> {
+        if let Some(name) = &self.name {
+            return vec![(self.group.as_deref(), name)];
+        }
+
+        let Some(title) = self.title.as_deref() else {
+            return Vec::new();
+        };
+
+        let mut list: Vec<(Option<&str>, &str)> = title
+            .match_indices(" - ")
+            .map(|(i, x)| (Some(title[..i].trim()), title[i + x.len()..].trim()))
+            .collect();
+
+        list.push((None, title));
+        list
+    }
+
+    /// Get the track key for the entry (see Track::get_key).
+    fn get_key(&self) -> String {
+        match self.offset {
+            Some((start, _)) => format!("{}#t={}", self.path, start.as_secs_f64()),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Find the entry in the library, by path. If the path is not found, fall back to the group, album and track name, and then to the file name.
+    pub fn resolve(&self, library: &mut Library) -> Option<(usize, usize, usize)> {
+        if let Some(entry) = library.find_entry(&self.get_key()) {
+            return Some(entry);
+        }
+
+        // compare names by letter and digit only, i.e. "Don't Stop" matches "dont stop".
+        let simple = |text: &str| -> String {
+            text.chars()
+                .filter(|x| x.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect()
+        };
+
+        let album = self.album.as_deref().map(simple);
+
+        for (group, name) in self.get_name_list() {
+            let group = group.map(simple);
+            let name = simple(name);
+
+            for (i_group, entry) in library.list_group.iter().enumerate() {
+                if group.as_ref().is_some_and(|x| *x != simple(&entry.name)) {
+                    continue;
+                }
+
+                for (i_album, entry) in entry.list_album.iter().enumerate() {
+                    if album.as_ref().is_some_and(|x| *x != simple(&entry.name)) {
+                        continue;
+                    }
+
+                    for (i_track, entry) in entry.list_track.iter().enumerate() {
+                        if simple(&entry.name) == name {
+                            return Some((i_group, i_album, i_track));
+                        }
+                    }
+                }
+            }
+        }
+
+        // the library may have been moved, so look for a single track with the same file name.
+        let file = Path::new(&self.path)
+            .file_name()?
+            .to_string_lossy()
+            .to_lowercase();
+        let mut list = library.list_key.iter().filter(|(key, _)| {
+            Path::new(key)
+                .file_name()
+                .is_some_and(|x| x.to_string_lossy().to_lowercase() == file)
+        });
+
+        match (list.next(), list.next()) {
+            (Some((_, entry)), None) => Some(*entry),
+            _ => None,
+        }
+    }
+
+    /// Get a description of the entry, for the user.
+    pub fn get_label(&self) -> String {
+        let title = self.get_title();
+
+        if title.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{title} ({})", self.path)
+        }
+    }
+}
+
+impl Store for Playlist {
+    const PATH: &'static str = "playlist.data";
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, data: &[u8]) -> Option<Self> {
+        if version != 0 {
+            return None;
+        }
+
+        if let Some(playlist) = decode(data) {
+            return Some(playlist);
+        }
+
+        let legacy: PlaylistLegacy = decode(data)?;
+        let mut playlist = Self::default();
+
+        playlist.list_entry = legacy
+            .list_entry
+            .into_iter()
+            .map(|entry| PlaylistEntry {
+                name: entry.name,
+                list_track: entry.list_track,
+                rule: None,
+                list_match: None,
+            })
+            .collect();
+
+        Some(playlist)
+    }
+}
+
+impl Drop for Playlist {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+//================================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_list() -> Vec<FileEntry> {
+        vec![
+            FileEntry {
+                path: "/music/AC/DC & Co/01 <Intro>.flac".to_string(),
+                group: Some("AC/DC & Co".to_string()),
+                album: Some("Live \"1991\"".to_string()),
+                name: Some("Intro".to_string()),
+                title: None,
+                time: Some(95),
+                offset: None,
+            },
+            FileEntry {
+                path: "/music/Ünïcødé/album.flac".to_string(),
+                group: Some("Ünïcødé".to_string()),
+                album: Some("Album".to_string()),
+                name: Some("Second".to_string()),
+                title: None,
+                time: Some(240),
+                offset: Some((
+                    Duration::from_millis(243_360),
+                    Duration::from_millis(483_360),
+                )),
+            },
+        ]
+    }
+
+    /// Check a read list against the written list. A format may only have a "group - name" title, or no CUE sheet offset.
+    fn check(list: &[FileEntry], title: bool, offset: bool) {
+        let source = get_list();
+
+        assert_eq!(list.len(), source.len());
+
+        for (entry, source) in list.iter().zip(source.iter()) {
+            assert_eq!(entry.path, source.path);
+            assert_eq!(entry.time, source.time);
+
+            if offset {
+                assert_eq!(entry.offset, source.offset);
+            } else {
+                assert_eq!(entry.offset, None);
+            }
+
+            if title {
+                assert_eq!(entry.get_title(), source.get_title());
+            } else {
+                assert_eq!(entry.group, source.group);
+                assert_eq!(entry.album, source.album);
+                assert_eq!(entry.name, source.name);
+            }
+        }
+    }
+
+    #[test]
+    fn m3u() {
+        let data = Playlist::write_m3u(&get_list());
+
+        check(&Playlist::read_m3u(&data), true, true);
+    }
+
+    #[test]
+    fn pls() {
+        let data = Playlist::write_pls(&get_list());
+
+        check(&Playlist::read_pls(&data), true, false);
+    }
+
+    #[test]
+    fn xspf() {
+        let data = Playlist::write_xspf(&get_list());
+
+        check(&Playlist::read_xspf(&data), false, true);
+    }
+
+    #[test]
+    fn xspf_other_writer() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <trackList>
+    <track id="1">
+      <location><![CDATA[file:///music/a&b.flac]]></location>
+      <title>Rock &#38; Roll &#x26; More</title>
+      <creator>Group</creator>
+    </track>
+    <track/>
+    <track>
+      <location>file:///music/c%20d.mp3</location>
+      <title><![CDATA[<Live>]]> &amp; Loud</title>
+    </track>
+  </trackList>
+</playlist>
+"#;
+        let list = Playlist::read_xspf(data);
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].path, "/music/a&b.flac");
+        assert_eq!(list[0].name.as_deref(), Some("Rock & Roll & More"));
+        assert_eq!(list[0].group.as_deref(), Some("Group"));
+        assert_eq!(list[1].path, "/music/c d.mp3");
+        assert_eq!(list[1].name.as_deref(), Some("<Live> & Loud"));
+    }
+
+    #[test]
+    fn name_list() {
+        let mut entry = FileEntry::new("/music/a.flac");
+
+        entry.title = Some("Group - Name - Part".to_string());
+
+        assert_eq!(
+            entry.get_name_list(),
+            vec![
+                (Some("Group"), "Name - Part"),
+                (Some("Group - Name"), "Part"),
+                (None, "Group - Name - Part"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,841 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{app::*, library::*, player::*};
+
+//================================================================
+
+use eframe::egui;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use std::sync::mpsc::Sender;
+
+//================================================================
+
+/// Play queue and play state. Every queue modification goes through here, so that the queue index, shuffle permutation, play history and undo state are always consistent with the queue.
+pub struct Queue {
+    /// every queue entry, for group, album, track.
+    pub list: Vec<(usize, usize, usize)>,
+    /// queue index of the current entry.
+    pub index: usize,
+    /// id of every queue entry, in step with the queue list. an entry keeps its id wherever it moves, and an id is never given to another entry.
+    list_id: Vec<usize>,
+    /// id for the next new queue entry.
+    id: usize,
+    /// play state, for group, album, track.
+    pub state: Option<(usize, usize, usize)>,
+    /// repeat mode.
+    pub repeat: Repeat,
+    /// stop mode, will reset on use.
+    pub stop: Stop,
+    /// randomize queue.
+    pub random: Random,
+    /// shuffle state, a permutation of every queue index yet to be played. the next index is at the end.
+    pub shuffle: Vec<usize>,
+    /// play history, every queue index that has been played. the most recent index is at the end.
+    pub history: Vec<usize>,
+    /// queue selection state, for every selected queue index in the queue layout. kept in step with every queue modification.
+    pub pick: Vec<usize>,
+    /// queue undo state, a list of queue snapshots. the most recent snapshot is at the end.
+    pub list_undo: Vec<Record>,
+    /// queue redo state, a list of queue snapshots. the most recent snapshot is at the end.
+    pub list_redo: Vec<Record>,
+    /// play-back event sender, for every queue modification.
+    event: Sender<PlayerEvent>,
+    /// count of every queue modification and shuffle permutation, to tell if the play order has changed (see Queue::get_version).
+    version: usize,
+    /// seed for the next shuffle permutation, so that a copy of the queue will shuffle the same way (see Queue::get_copy).
+    seed: u64,
+}
+
+/// every value that decides the play order from the current entry on: modification count, queue index, play state, and every mode.
+pub type Version = (
+    usize,
+    usize,
+    Option<(usize, usize, usize)>,
+    Repeat,
+    Stop,
+    Random,
+);
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Repeat {
+    /// stop at the end of the queue.
+    Off,
+    /// repeat the current track.
+    One,
+    /// repeat the queue.
+    All,
+}
+
+impl Repeat {
+    /// Get the next repeat mode, for cycling through each mode.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::All,
+            Self::All => Self::One,
+            Self::One => Self::Off,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Stop {
+    Off,
+    /// stop after the current track.
+    Track,
+    /// stop after the current album.
+    Album,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Random {
+    /// play the queue in order.
+    Off,
+    /// play every track in a random order.
+    Track,
+    /// play every album in a random order, keeping the track order within an album.
+    Album,
+}
+
+impl Random {
+    /// Get the next random mode, for cycling through each mode.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Track,
+            Self::Track => Self::Album,
+            Self::Album => Self::Off,
+        }
+    }
+}
+
+/// queue snapshot, for undo/redo.
+pub struct Record {
+    /// queue entry list.
+    list: Vec<(usize, usize, usize)>,
+    /// queue entry id list.
+    list_id: Vec<usize>,
+    /// queue index.
+    index: usize,
+    /// shuffle state.
+    shuffle: Vec<usize>,
+    /// play history.
+    history: Vec<usize>,
+}
+
+impl Queue {
+    /// maximum queue undo/redo depth.
+    const UNDO_SIZE: usize = 64;
+
+    pub fn new(event: Sender<PlayerEvent>) -> Self {
+        Self {
+            list: Vec::default(),
+            index: 0,
+            list_id: Vec::default(),
+            id: 0,
+            state: None,
+            repeat: Repeat::Off,
+            stop: Stop::Off,
+            random: Random::Off,
+            shuffle: Vec::default(),
+            history: Vec::default(),
+            pick: Vec::default(),
+            list_undo: Vec::default(),
+            list_redo: Vec::default(),
+            event,
+            version: 0,
+            seed: rand::random(),
+        }
+    }
+
+    /// Clear the queue, and stop play-back.
+    pub fn reset(app: &mut App) {
+        if !app.queue.list.is_empty() {
+            app.queue.record();
+        }
+
+        app.queue.change();
+        app.queue.list.clear();
+        app.queue.list_id.clear();
+        app.queue.index = 0;
+        app.queue.shuffle.clear();
+        app.queue.history.clear();
+        app.queue.pick.clear();
+        app.track_stop(false);
+    }
+
+    /// Replace the queue with a list of entries, and play from the given index in it.
+    pub fn play(
+        app: &mut App,
+        list: Vec<(usize, usize, usize)>,
+        index: usize,
+        context: &egui::Context,
+    ) -> anyhow::Result<()> {
+        Self::reset(app);
+        app.queue.list_id = app.queue.make_id(list.len());
+        app.queue.list = list;
+
+        if let Some(track) = app.queue.list.get(index).copied() {
+            app.queue.index = index;
+            app.track_add(track, context)?;
+        }
+
+        app.queue.shuffle_reset();
+
+        Ok(())
+    }
+
+    /// Add a list of entries to the queue, either after the current entry or at the end. Will begin play-back if nothing is playing.
+    pub fn add(
+        app: &mut App,
+        list: Vec<(usize, usize, usize)>,
+        next: bool,
+        context: &egui::Context,
+    ) -> anyhow::Result<()> {
+        let Some(first) = list.first().copied() else {
+            return Ok(());
+        };
+
+        let play = app.queue.state.is_none();
+        let index = if next && !play {
+            app.queue.index + 1
+        } else {
+            app.queue.list.len()
+        };
+
+        app.queue.insert(index, &list, next);
+
+        if play {
+            app.queue.jump(index);
+            app.track_add(first, context)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove a list of queue entries. If the current entry is removed, play the next entry.
+    pub fn detach(app: &mut App, list: &[usize], context: &egui::Context) -> anyhow::Result<()> {
+        if app.queue.remove(list) {
+            let length = app.queue.list.len();
+            let next = match app.queue.random {
+                Random::Off => Some(app.queue.index).filter(|x| *x < length),
+                _ => app.queue.shuffle.pop(),
+            };
+
+            // the last entry was removed, wrap around if the queue is on repeat.
+            let next = next.or_else(|| {
+                if app.queue.repeat != Repeat::All || length == 0 {
+                    return None;
+                }
+
+                match app.queue.random {
+                    Random::Off => Some(0),
+                    _ => {
+                        app.queue.shuffle_reset();
+                        app.queue.shuffle.pop().or(Some(0))
+                    }
+                }
+            });
+
+            if let Some(index) = next
+                && let Some(track) = app.queue.list.get(index).copied()
+            {
+                app.queue.index = index;
+                app.track_add(track, context)?;
+            } else {
+                app.track_stop(false);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the random mode, with a new shuffle permutation.
+    pub fn set_random(&mut self, random: Random) {
+        self.random = random;
+        self.shuffle_reset();
+    }
+
+    /// Re-map every queue entry and the play state from an old library to the current one by track key (see Track::get_key), after a library scan. Every entry no longer in the library is dropped, and play-back will stop if the current track is gone.
+    pub fn rescan(app: &mut App, library: &Library) {
+        let map = |entry: (usize, usize, usize)| {
+            let album = library.list_group.get(entry.0)?.list_album.get(entry.1)?;
+            let track = album.list_track.get(entry.2)?;
+
+            app.library.get_entry(&track.get_key())
+        };
+
+        if app.queue.relink(map) {
+            app.track_stop(false);
+        }
+    }
+
+    /// Generate a new shuffle permutation from every queue index, except for the current one.
+    pub fn shuffle_reset(&mut self) {
+        let mut picker = StdRng::seed_from_u64(self.seed);
+        let active = self.state.map(|_| self.index);
+
+        self.seed = picker.random();
+        self.version += 1;
+        self.shuffle.clear();
+
+        match self.random {
+            Random::Off => {}
+            Random::Track => {
+                self.shuffle = (0..self.list.len())
+                    .filter(|x| Some(*x) != active)
+                    .collect();
+                self.shuffle.shuffle(&mut picker);
+            }
+            Random::Album => {
+                let mut list: Vec<((usize, usize), Vec<usize>)> = Vec::new();
+
+                // gather every queue index by album, in queue order.
+                for (i, (group, album, _)) in self.list.iter().enumerate() {
+                    if Some(i) == active {
+                        continue;
+                    }
+
+                    match list.iter_mut().find(|(key, _)| *key == (*group, *album)) {
+                        Some((_, entry)) => entry.push(i),
+                        None => list.push(((*group, *album), vec![i])),
+                    }
+                }
+
+                list.shuffle(&mut picker);
+
+                // the next index is at the end, so reverse the list to keep the track order within an album.
+                self.shuffle = list.into_iter().flat_map(|(_, entry)| entry).collect();
+                self.shuffle.reverse();
+            }
+        }
+    }
+
+    /// Get the next queue index to play, following the shuffle permutation if the queue is randomized. Will wrap around if the queue is on repeat.
+    pub fn next(&mut self) -> Option<usize> {
+        if let Some(next) = self.peek() {
+            if self.random != Random::Off {
+                self.shuffle.pop();
+            }
+
+            return Some(next);
+        }
+
+        if self.repeat != Repeat::All || self.list.is_empty() {
+            return None;
+        }
+
+        match self.random {
+            Random::Off => Some(0),
+            _ => {
+                self.shuffle_reset();
+                // the current index is the only index in the queue.
+                self.shuffle.pop().or(Some(self.index))
+            }
+        }
+    }
+
+    /// Move to the queue entry to play once the current entry has ended, following the stop, repeat and random mode. Returns none if play-back should stop instead, resetting the stop mode.
+    pub fn advance(&mut self) -> Option<(usize, usize, usize)> {
+        let active = self.state?;
+
+        let stop = match self.stop {
+            Stop::Off => false,
+            Stop::Track => true,
+            Stop::Album => {
+                // stop if the next track is not from the same album.
+                let next = self.peek().and_then(|x| self.list.get(x));
+
+                next.is_none_or(|next| (next.0, next.1) != (active.0, active.1))
+            }
+        };
+
+        if stop {
+            self.stop = Stop::Off;
+            return None;
+        }
+
+        if self.repeat == Repeat::One {
+            return Some(active);
+        }
+
+        // end of the queue.
+        let index = self.next()?;
+        let entry = self.list.get(index).copied()?;
+
+        self.jump(index);
+        self.state = Some(entry);
+
+        Some(entry)
+    }
+
+    /// Get the next queue index to play without moving to it. Will not wrap around.
+    pub fn peek(&self) -> Option<usize> {
+        match self.random {
+            Random::Off => {
+                let next = self.index + 1;
+                (next < self.list.len()).then_some(next)
+            }
+            _ => self.shuffle.last().copied(),
+        }
+    }
+
+    /// Get the previous queue index to play, following the play history if the queue is randomized.
+    pub fn back(&mut self) -> Option<usize> {
+        match self.random {
+            Random::Off => self.index.checked_sub(1),
+            _ => {
+                let index = self.history.pop()?;
+                // put the current index back, so skipping forward will play it again.
+                self.shuffle.push(self.index);
+                Some(index)
+            }
+        }
+    }
+
+    /// Move to a given queue index, recording the current one in the play history.
+    pub fn jump(&mut self, index: usize) {
+        if self.state.is_some() {
+            self.history.push(self.index);
+        }
+
+        self.shuffle.retain(|x| *x != index);
+        self.index = index;
+    }
+
+    /// Get a copy of the play order, i.e. for the player thread, without the play history, the queue selection or any undo/redo state. The copy will advance (see Queue::advance) exactly as the queue itself would.
+    pub fn get_copy(&self) -> Self {
+        Self {
+            list: self.list.clone(),
+            index: self.index,
+            list_id: Vec::default(),
+            id: self.id,
+            state: self.state,
+            repeat: self.repeat,
+            stop: self.stop,
+            random: self.random,
+            shuffle: self.shuffle.clone(),
+            history: Vec::default(),
+            pick: Vec::default(),
+            list_undo: Vec::default(),
+            list_redo: Vec::default(),
+            event: self.event.clone(),
+            version: self.version,
+            seed: self.seed,
+        }
+    }
+
+    /// Get the play order version. Any change to the play order will give a different version.
+    pub fn get_version(&self) -> Version {
+        (
+            self.version,
+            self.index,
+            self.state,
+            self.repeat,
+            self.stop,
+            self.random,
+        )
+    }
+
+    /// Get a snapshot of the queue.
+    fn snapshot(&self) -> Record {
+        Record {
+            list: self.list.clone(),
+            list_id: self.list_id.clone(),
+            index: self.index,
+            shuffle: self.shuffle.clone(),
+            history: self.history.clone(),
+        }
+    }
+
+    /// Record the queue state before a modification, for undo. Will discard every redo snapshot.
+    fn record(&mut self) {
+        self.change();
+
+        let record = self.snapshot();
+
+        self.list_undo.push(record);
+        self.list_redo.clear();
+
+        if self.list_undo.len() > Self::UNDO_SIZE {
+            self.list_undo.remove(0);
+        }
+    }
+
+    /// Restore a queue snapshot. The current track will keep playing, and the queue index will follow it if it's still in the queue.
+    fn restore(&mut self, record: Record) {
+        self.change();
+
+        self.list = record.list;
+        self.list_id = record.list_id;
+        self.index = record.index;
+        self.shuffle = record.shuffle;
+        self.history = record.history;
+        self.pick.clear();
+
+        if let Some(active) = self.state
+            && self.list.get(self.index) != Some(&active)
+            && let Some(index) = self.list.iter().position(|x| *x == active)
+        {
+            self.index = index;
+            self.shuffle.retain(|x| *x != index);
+        }
+    }
+
+    /// Send a queue change event, on every queue modification.
+    fn change(&mut self) {
+        self.version += 1;
+
+        let _ = self.event.send(PlayerEvent::QueueChanged);
+    }
+
+    /// Drop every queue selection index that is no longer in the queue, after a queue modification from elsewhere.
+    pub fn refresh(&mut self) {
+        let length = self.list.len();
+
+        self.pick.retain(|x| *x < length);
+    }
+
+    /// Undo the last queue modification. Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(record) = self.list_undo.pop() else {
+            return false;
+        };
+
+        let current = self.snapshot();
+        self.list_redo.push(current);
+        self.restore(record);
+
+        true
+    }
+
+    /// Redo the last undone queue modification. Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(record) = self.list_redo.pop() else {
+            return false;
+        };
+
+        let current = self.snapshot();
+        self.list_undo.push(current);
+        self.restore(record);
+
+        true
+    }
+
+    /// Re-map every queue index in the shuffle permutation, play history and queue selection. An index mapped to none will be discarded.
+    fn remap(&mut self, map: impl Fn(usize) -> Option<usize>) {
+        for list in [&mut self.shuffle, &mut self.history, &mut self.pick] {
+            *list = list.iter().filter_map(|x| map(*x)).collect();
+        }
+    }
+
+    /// Re-map every queue entry and the play state to a different library. An entry mapped to none will be dropped, keeping the queue index, shuffle permutation and play history consistent. Returns true if the current entry was dropped.
+    fn relink(
+        &mut self,
+        map: impl Fn((usize, usize, usize)) -> Option<(usize, usize, usize)>,
+    ) -> bool {
+        self.change();
+
+        let list: Vec<_> = self.list.iter().map(|x| map(*x)).collect();
+        let below = |x: usize| {
+            list[..x.min(list.len())]
+                .iter()
+                .filter(|y| y.is_none())
+                .count()
+        };
+
+        self.remap(|x| list.get(x)?.map(|_| x - below(x)));
+        self.index -= below(self.index);
+        self.list_id = (self.list_id.iter().zip(&list))
+            .filter_map(|(id, entry)| entry.map(|_| *id))
+            .collect();
+        self.list = list.into_iter().flatten().collect();
+
+        // every queue snapshot refers to the old library.
+        self.list_undo.clear();
+        self.list_redo.clear();
+
+        let active = self.state.is_some();
+        self.state = self.state.and_then(map);
+
+        active && self.state.is_none()
+    }
+
+    /// Insert a list of entries into the queue. If the queue is randomized, each entry will either be played next, or at a random point.
+    pub fn insert(&mut self, index: usize, list: &[(usize, usize, usize)], next: bool) {
+        let size = list.len();
+
+        self.record();
+
+        self.remap(|x| Some(if x >= index { x + size } else { x }));
+
+        if self.state.is_some() && self.index >= index {
+            self.index += size;
+        }
+
+        self.list.splice(index..index, list.iter().copied());
+        let id = self.make_id(size);
+        self.list_id.splice(index..index, id);
+
+        let mut picker = rand::rng();
+
+        match self.random {
+            Random::Off => {}
+            // the next index is at the end, so push in reverse to keep the order.
+            _ if next => self.shuffle.extend((index..index + size).rev()),
+            Random::Track => {
+                for x in index..index + size {
+                    let at = picker.random_range(0..=self.shuffle.len());
+                    self.shuffle.insert(at, x);
+                }
+            }
+            // keep every entry together, to be played last.
+            Random::Album => {
+                self.shuffle.splice(0..0, (index..index + size).rev());
+            }
+        }
+    }
+
+    /// Remove a list of queue entries, keeping the queue index, shuffle permutation and play history consistent. Returns true if the current entry was removed, in which case the queue index will point at the next entry left.
+    pub fn remove(&mut self, list: &[usize]) -> bool {
+        if list.is_empty() {
+            return false;
+        }
+
+        self.record();
+
+        let below = |x: usize| list.iter().filter(|y| **y < x).count();
+        let active = self.state.is_some() && list.contains(&self.index);
+
+        self.remap(|x| (!list.contains(&x)).then(|| x - below(x)));
+        self.index -= below(self.index);
+
+        (self.list, self.list_id) = (self.list.iter().zip(&self.list_id))
+            .enumerate()
+            .filter(|(i, _)| !list.contains(i))
+            .map(|(_, (entry, id))| (*entry, *id))
+            .unzip();
+
+        active
+    }
+
+    /// Move a queue entry to a different position in the queue.
+    pub fn shift(&mut self, from: usize, to: usize) {
+        self.record();
+
+        let map = |x: usize| {
+            if x == from {
+                to
+            } else if from < to && x > from && x <= to {
+                x - 1
+            } else if from > to && x >= to && x < from {
+                x + 1
+            } else {
+                x
+            }
+        };
+
+        self.remap(|x| Some(map(x)));
+        self.index = map(self.index);
+
+        let entry = self.list.remove(from);
+        self.list.insert(to, entry);
+        let id = self.list_id.remove(from);
+        self.list_id.insert(to, id);
+    }
+
+    /// Get the id of a queue entry, by queue index.
+    pub fn get_id(&self, index: usize) -> Option<usize> {
+        self.list_id.get(index).copied()
+    }
+
+    /// Get the queue index of a queue entry, by id.
+    pub fn get_index(&self, id: usize) -> Option<usize> {
+        self.list_id.iter().position(|x| *x == id)
+    }
+
+    /// Make a new id for every new queue entry.
+    fn make_id(&mut self, size: usize) -> Vec<usize> {
+        let list = (self.id..self.id + size).collect();
+
+        self.id += size;
+
+        list
+    }
+
+    /// Get every queue index that has already been played.
+    pub fn played(&self) -> Vec<usize> {
+        match self.random {
+            Random::Off => (0..self.index).collect(),
+            _ => {
+                let mut list: Vec<usize> = self
+                    .history
+                    .iter()
+                    .copied()
+                    .filter(|x| *x != self.index)
+                    .collect();
+                list.sort();
+                list.dedup();
+                list
+            }
+        }
+    }
+}
+
+//================================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_queue(list: Vec<(usize, usize, usize)>, index: usize) -> Queue {
+        let mut queue = Queue::new(std::sync::mpsc::channel().0);
+
+        queue.list_id = queue.make_id(list.len());
+        queue.list = list;
+        queue.index = index;
+        queue.state = queue.list.get(index).copied();
+
+        queue
+    }
+
+    #[test]
+    fn relink() {
+        let mut queue = get_queue(vec![(0, 0, 0), (0, 0, 1), (0, 0, 2), (0, 0, 3)], 2);
+        queue.shuffle = vec![3, 1];
+        queue.history = vec![0, 1];
+        queue.pick = vec![1, 3];
+
+        // the second entry is gone, and every other entry has moved to a different group.
+        let stop = queue.relink(|x| (x.2 != 1).then_some((1, 0, x.2)));
+
+        assert!(!stop);
+        assert_eq!(queue.list, vec![(1, 0, 0), (1, 0, 2), (1, 0, 3)]);
+        assert_eq!(queue.index, 1);
+        assert_eq!(queue.state, Some((1, 0, 2)));
+        assert_eq!(queue.shuffle, vec![2]);
+        assert_eq!(queue.history, vec![0]);
+        assert_eq!(queue.pick, vec![2]);
+        assert_eq!(queue.list_id, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn relink_active() {
+        let mut queue = get_queue(vec![(0, 0, 0), (0, 0, 1), (0, 0, 2)], 1);
+
+        // the current entry is gone, so the queue index will point at the next entry left.
+        assert!(queue.relink(|x| (x.2 != 1).then_some(x)));
+        assert_eq!(queue.list, vec![(0, 0, 0), (0, 0, 2)]);
+        assert_eq!(queue.index, 1);
+        assert_eq!(queue.state, None);
+
+        // nothing was playing, so there is nothing to stop.
+        let mut queue = get_queue(Vec::new(), 0);
+
+        assert!(!queue.relink(|_| None));
+    }
+
+    #[test]
+    fn id() {
+        let mut queue = get_queue(vec![(0, 0, 0), (0, 0, 1)], 0);
+
+        queue.insert(1, &[(0, 0, 2), (0, 0, 3)], false);
+        assert_eq!(queue.list_id, vec![0, 2, 3, 1]);
+
+        queue.remove(&[0, 2]);
+        queue.shift(1, 0);
+        assert_eq!(queue.list_id, vec![1, 2]);
+        assert_eq!(queue.get_index(1), Some(0));
+        assert_eq!(queue.get_index(0), None);
+
+        // an id is never given to another entry, even after an undo.
+        queue.undo();
+        queue.insert(0, &[(0, 0, 4)], false);
+        assert_eq!(queue.list_id, vec![4, 2, 1]);
+        assert_eq!(queue.get_id(0), Some(4));
+    }
+
+    #[test]
+    fn advance() {
+        let mut queue = get_queue(vec![(0, 0, 0), (0, 0, 1), (0, 1, 0)], 0);
+
+        assert_eq!(queue.advance(), Some((0, 0, 1)));
+        assert_eq!(queue.index, 1);
+
+        // stop at the end of the album, and reset the stop mode.
+        queue.stop = Stop::Album;
+        assert_eq!(queue.advance(), None);
+        assert!(queue.stop == Stop::Off);
+
+        queue.repeat = Repeat::One;
+        assert_eq!(queue.advance(), Some((0, 0, 1)));
+        assert_eq!(queue.index, 1);
+
+        queue.repeat = Repeat::Off;
+        assert_eq!(queue.advance(), Some((0, 1, 0)));
+        assert_eq!(queue.advance(), None);
+    }
+
+    #[test]
+    fn advance_copy() {
+        let mut queue = get_queue((0..16).map(|x| (0, x, 0)).collect(), 0);
+        queue.repeat = Repeat::All;
+        queue.set_random(Random::Track);
+
+        let mut copy = queue.get_copy();
+
+        // a copy will wrap around with the same shuffle permutation.
+        for _ in 0..64 {
+            assert_eq!(queue.advance(), copy.advance());
+            assert_eq!(queue.index, copy.index);
+        }
+
+        assert!(queue.get_version() == copy.get_version());
+    }
+}
@@ -0,0 +1,825 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::{app::*, control::*, queue::*, search::*, setting::*, system::*};
+
+//================================================================
+
+use eframe::egui;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        mpsc::{Receiver, Sender, SyncSender, TryRecvError},
+    },
+    time::Duration,
+};
+use tungstenite::{WebSocket, http::StatusCode, protocol::Role};
+
+//================================================================
+
+/// remote API route, from a remote API client.
+pub enum Route {
+    /// play-back control, or play state.
+    Control(Request),
+    /// every group and album in the library.
+    Library,
+    /// every track in an album, by group and album index.
+    Album(usize, usize),
+    /// every track matching a search query (see Search).
+    Search(String),
+    /// every queue entry, and the current queue index.
+    Queue,
+    /// add a list of tracks to the queue, either after the current entry or at the end.
+    QueueAdd(Vec<(usize, usize, usize)>, bool),
+    /// remove a queue entry, by index.
+    QueueRemove(usize),
+    /// play a queue entry, by index.
+    QueuePlay(usize),
+    /// clear the queue.
+    QueueClear,
+}
+
+/// remote API route, with a sender for the response, as an HTTP status code and a JSON body.
+pub type Call = (Route, Sender<(u16, Value)>);
+
+/// queue add request body.
+#[derive(Deserialize)]
+struct QueueAdd {
+    list: Vec<(usize, usize, usize)>,
+    #[serde(default)]
+    next: bool,
+}
+
+/// HTTP request, from a remote API client.
+struct HttpRequest {
+    /// request method, e.g. "GET".
+    method: String,
+    /// request URL, as the path and the query.
+    url: String,
+    /// every request header, as name and value.
+    list_header: Vec<(String, String)>,
+    /// request body, as given by the "Content-Length" header.
+    body: Vec<u8>,
+}
+
+/// play state event sender, for every connected WebSocket client.
+type SocketList = Arc<Mutex<Vec<SyncSender<String>>>>;
+
+/// last published play state, as status, track path and volume. none to publish on the next frame.
+type State = Arc<Mutex<Option<(String, Option<String>, f32)>>>;
+
+/// Remote API server, as HTTP with a JSON body, and a WebSocket for play state events. Every request must have the remote token, either as a bearer token or a "token" query parameter.
+pub struct Remote {
+    list_socket: SocketList,
+    last: State,
+}
+
+impl Remote {
+    /// maximum time to wait for the app to answer a request, or for a client to send or take a request.
+    const TIME_OUT: Duration = Duration::from_secs(5);
+    /// time-out of a WebSocket read, which is how often a WebSocket client is checked for a pending event.
+    const SOCKET_READ: Duration = Duration::from_millis(100);
+    /// time-out of a WebSocket write. a WebSocket client that can't be written to for this long is dropped.
+    const SOCKET_WRITE: Duration = Duration::from_secs(5);
+    /// maximum number of pending events for a WebSocket client. a client that falls further behind is dropped.
+    const SOCKET_QUEUE: usize = 64;
+    /// maximum size of a request's header, and of a request's body.
+    const SIZE_HEADER: usize = 16 * 1024;
+    const SIZE_BODY: usize = 1024 * 1024;
+
+    /// Listen for every remote API request, on a separate thread. Returns none if the remote API is disabled.
+    pub fn new(
+        setting: &Setting,
+        context: egui::Context,
+    ) -> anyhow::Result<Option<(Self, Receiver<Call>)>> {
+        if !setting.remote_allow {
+            return Ok(None);
+        }
+
+        let listener = TcpListener::bind(&setting.remote_address)
+            .map_err(|error| anyhow::anyhow!("Couldn't start remote API server: {error}"))?;
+        let list_socket = SocketList::default();
+        let last = State::default();
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let remote = Self {
+            list_socket: list_socket.clone(),
+            last: last.clone(),
+        };
+
+        let token = setting.remote_token.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let token = token.clone();
+                let tx = tx.clone();
+                let context = context.clone();
+                let list_socket = list_socket.clone();
+                let last = last.clone();
+
+                std::thread::spawn(move || {
+                    Self::handle(stream, &token, tx, context, (list_socket, last));
+                });
+            }
+        });
+
+        Ok(Some((remote, rx)))
+    }
+
+    /// Publish the play state to every WebSocket client, if it has changed.
+    pub fn publish(&self, response: &Response) {
+        let state = (
+            response.status.clone(),
+            response.path.clone(),
+            response.volume,
+        );
+        let mut last = self.last.lock().unwrap();
+
+        if last.as_ref() == Some(&state) {
+            return;
+        }
+
+        *last = Some(state);
+
+        let text = json!({ "event": "state", "state": response }).to_string();
+
+        // every client has its own writer, so this never waits on a client. drop every client that is gone, or too far behind.
+        self.list_socket
+            .lock()
+            .unwrap()
+            .retain(|socket| socket.try_send(text.clone()).is_ok());
+    }
+
+    /// Write every play state event to a WebSocket client, and answer every incoming frame (i.e. a ping or a close), until the client disconnects or is dropped.
+    fn handle_socket(mut socket: WebSocket<TcpStream>, event_rx: Receiver<String>) {
+        loop {
+            // every read will time out (see Remote::SOCKET_READ), so that every pending event is written in time. a pong or close reply is queued by the read itself.
+            match socket.read() {
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(error))
+                    if matches!(
+                        error.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) => {}
+                // the client has closed the connection, or the connection has failed.
+                Err(_) => return,
+            }
+
+            loop {
+                match event_rx.try_recv() {
+                    Ok(text) => {
+                        if socket.send(text.into()).is_err() {
+                            return;
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    // dropped from the client list, or the server is gone.
+                    Err(TryRecvError::Disconnected) => {
+                        let _ = socket.close(None);
+                        let _ = socket.flush();
+                        return;
+                    }
+                }
+            }
+
+            // write any pending pong or close reply.
+            if socket.flush().is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Answer a remote API route. Every route is answered with a JSON body.
+    pub fn make_route(
+        route: Route,
+        app: &mut App,
+        context: &egui::Context,
+    ) -> anyhow::Result<Value> {
+        match route {
+            Route::Control(request) => {
+                let error = System::make_request(request, app, context)
+                    .err()
+                    .map(|x| x.to_string());
+
+                return Ok(serde_json::to_value(Response::new(app, error))?);
+            }
+            Route::Library => {
+                let list: Vec<Value> = app
+                    .library
+                    .list_group
+                    .iter()
+                    .enumerate()
+                    .map(|(i_group, group)| {
+                        let list: Vec<Value> = group
+                            .list_album
+                            .iter()
+                            .enumerate()
+                            .map(|(i_album, album)| json!({ "index": i_album, "name": album.name }))
+                            .collect();
+
+                        json!({ "index": i_group, "name": group.name, "album": list })
+                    })
+                    .collect();
+
+                return Ok(Value::Array(list));
+            }
+            Route::Album(i_group, i_album) => {
+                let album = app
+                    .library
+                    .list_group
+                    .get(i_group)
+                    .and_then(|x| x.list_album.get(i_album))
+                    .ok_or(anyhow::anyhow!("Invalid group or album index."))?;
+
+                let list: Vec<Value> = (0..album.list_track.len())
+                    .map(|i_track| Self::get_track(app, (i_group, i_album, i_track)))
+                    .collect();
+
+                return Ok(Value::Array(list));
+            }
+            Route::Search(query) => {
+                let list: Vec<Value> = Search::new(&query)?
+                    .run(&app.library)
+                    .into_iter()
+                    .map(|x| Self::get_track(app, x))
+                    .collect();
+
+                return Ok(Value::Array(list));
+            }
+            Route::Queue => {}
+            Route::QueueAdd(list, next) => {
+                let valid = list.iter().all(|entry| {
+                    app.library
+                        .list_group
+                        .get(entry.0)
+                        .and_then(|x| x.list_album.get(entry.1))
+                        .and_then(|x| x.list_track.get(entry.2))
+                        .is_some()
+                });
+
+                if !valid {
+                    return Err(anyhow::anyhow!("Invalid track index."));
+                }
+
+                Queue::add(app, list, next, context)?;
+            }
+            Route::QueueRemove(index) => {
+                if index >= app.queue.list.len() {
+                    return Err(anyhow::anyhow!("Invalid queue index."));
+                }
+
+                Queue::detach(app, &[index], context)?;
+            }
+            Route::QueuePlay(index) => {
+                let Some(track) = app.queue.list.get(index).copied() else {
+                    return Err(anyhow::anyhow!("Invalid queue index."));
+                };
+
+                app.queue.jump(index);
+                app.track_add(track, context)?;
+            }
+            Route::QueueClear => Queue::reset(app),
+        }
+
+        // every queue route is answered with the queue.
+        let list: Vec<Value> = app
+            .queue
+            .list
+            .iter()
+            .map(|x| Self::get_track(app, *x))
+            .collect();
+
+        Ok(json!({ "index": app.queue.index, "list": list }))
+    }
+
+    fn get_track(app: &App, index: (usize, usize, usize)) -> Value {
+        let (group, album, track) = app.get_state(index);
+
+        json!({
+            "index": [index.0, index.1, index.2],
+            "group": group.name,
+            "album": album.name,
+            "name": track.name,
+            "time": track.time.as_secs(),
+            "path": track.path,
+        })
+    }
+
+    /// Answer a single HTTP request, or upgrade it to a WebSocket.
+    fn handle(
+        mut stream: TcpStream,
+        token: &str,
+        tx: Sender<Call>,
+        context: egui::Context,
+        (list_socket, last): (SocketList, State),
+    ) {
+        // a client that doesn't send its request, or doesn't take the response, can't hold the connection forever.
+        if stream.set_read_timeout(Some(Self::TIME_OUT)).is_err()
+            || stream.set_write_timeout(Some(Self::TIME_OUT)).is_err()
+        {
+            return;
+        }
+
+        let (request, rest) = match Self::read_request(&mut stream) {
+            Ok(request) => request,
+            Err((code, error)) => {
+                Self::respond(&mut stream, code, json!({ "error": error }));
+                return;
+            }
+        };
+
+        let (path, query) = Self::get_query(&request.url);
+
+        if !Self::is_allowed(&request, &query, token) {
+            Self::respond(&mut stream, 401, json!({ "error": "Invalid token." }));
+            return;
+        }
+
+        if path == "/socket" {
+            let Some(key) = Self::get_header(&request, "Sec-WebSocket-Key") else {
+                Self::respond(
+                    &mut stream,
+                    400,
+                    json!({ "error": "Not a WebSocket request." }),
+                );
+                return;
+            };
+
+            let accept = tungstenite::handshake::derive_accept_key(key.as_bytes());
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+            );
+
+            if stream.write_all(response.as_bytes()).is_err()
+                || stream.set_read_timeout(Some(Self::SOCKET_READ)).is_err()
+                || stream.set_write_timeout(Some(Self::SOCKET_WRITE)).is_err()
+            {
+                return;
+            }
+
+            // a client may send a frame right after its request, so hand any of it to the WebSocket.
+            let socket = WebSocket::from_partially_read(stream, rest, Role::Server, None);
+            let (event_tx, event_rx) = std::sync::mpsc::sync_channel(Self::SOCKET_QUEUE);
+
+            list_socket.lock().unwrap().push(event_tx);
+
+            // publish the play state to the new client on the next frame.
+            *last.lock().unwrap() = None;
+            context.request_repaint();
+
+            // every connection has its own thread, so this one will serve the client from now on.
+            Self::handle_socket(socket, event_rx);
+            return;
+        }
+
+        let route = match Self::get_route(&request, path, &query) {
+            Ok(route) => route,
+            Err((code, error)) => {
+                Self::respond(&mut stream, code, json!({ "error": error }));
+                return;
+            }
+        };
+
+        let (response_tx, response_rx) = std::sync::mpsc::channel();
+
+        if tx.send((route, response_tx)).is_err() {
+            return;
+        }
+
+        context.request_repaint();
+
+        let (code, body) = response_rx
+            .recv_timeout(Self::TIME_OUT)
+            .unwrap_or((504, json!({ "error": "Timed out waiting for a response." })));
+
+        Self::respond(&mut stream, code, body);
+    }
+
+    /// Read an HTTP request, with the body. Returns the request, and any data sent after it.
+    fn read_request(stream: &mut impl Read) -> Result<(HttpRequest, Vec<u8>), (u16, String)> {
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+
+        let (request, mut rest, size) = loop {
+            let mut list_header = [httparse::EMPTY_HEADER; 64];
+            let mut parse = httparse::Request::new(&mut list_header);
+
+            if let httparse::Status::Complete(length) = parse
+                .parse(&data)
+                .map_err(|error| (400, error.to_string()))?
+            {
+                let request = HttpRequest {
+                    method: parse.method.unwrap_or_default().to_string(),
+                    url: parse.path.unwrap_or_default().to_string(),
+                    list_header: parse
+                        .headers
+                        .iter()
+                        .map(|x| {
+                            let value = String::from_utf8_lossy(x.value);
+                            (x.name.to_string(), value.to_string())
+                        })
+                        .collect(),
+                    body: Vec::new(),
+                };
+
+                if Self::get_header(&request, "Transfer-Encoding").is_some() {
+                    return Err((
+                        411,
+                        "Only a body with a \"Content-Length\" is supported.".to_string(),
+                    ));
+                }
+
+                let size = match Self::get_header(&request, "Content-Length") {
+                    Some(size) => size
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| (400, "Invalid \"Content-Length\".".to_string()))?,
+                    None => 0,
+                };
+
+                if size > Self::SIZE_BODY {
+                    return Err((413, "Request body is too large.".to_string()));
+                }
+
+                break (request, data.split_off(length), size);
+            }
+
+            if data.len() > Self::SIZE_HEADER {
+                return Err((431, "Request header is too large.".to_string()));
+            }
+
+            let size = Self::read(stream, &mut buffer)?;
+            data.extend_from_slice(&buffer[..size]);
+        };
+
+        while rest.len() < size {
+            let size = Self::read(stream, &mut buffer)?;
+            rest.extend_from_slice(&buffer[..size]);
+        }
+
+        let body = rest.drain(..size).collect();
+
+        Ok((HttpRequest { body, ..request }, rest))
+    }
+
+    /// Read from a client, failing if the client has closed the connection or has timed out.
+    fn read(stream: &mut impl Read, buffer: &mut [u8]) -> Result<usize, (u16, String)> {
+        match stream.read(buffer) {
+            Ok(0) => Err((
+                400,
+                "Connection closed before the request was complete.".to_string(),
+            )),
+            Ok(size) => Ok(size),
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                Err((408, "Timed out waiting for the request.".to_string()))
+            }
+            Err(error) => Err((400, error.to_string())),
+        }
+    }
+
+    #[rustfmt::skip]
+    fn get_route(request: &HttpRequest, path: &str, query: &HashMap<String, String>) -> Result<Route, (u16, String)> {
+        let list: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+        let index = |text: &str| text.parse::<usize>().map_err(|_| (400, format!("Invalid index \"{text}\".")));
+        let value = |key: &str| query.get(key).ok_or((400, format!("Missing \"{key}\" parameter.")));
+
+        let route = match (request.method.as_str(), list.as_slice()) {
+            ("GET",    ["status"])           => Route::Control(Request::Status),
+            ("POST",   ["play"])             => Route::Control(Request::Play),
+            ("POST",   ["pause"])            => Route::Control(Request::Pause),
+            ("POST",   ["toggle"])           => Route::Control(Request::Toggle),
+            ("POST",   ["next"])             => Route::Control(Request::Next),
+            ("POST",   ["previous"])         => Route::Control(Request::Previous),
+            ("POST",   ["stop"])             => Route::Control(Request::Stop),
+            ("POST",   ["quit"])             => Route::Control(Request::Quit),
+            ("POST",   ["seek"])             => Route::Control(Request::Seek {
+                time: value("time")?.parse().map_err(|_| (400, "Invalid time.".to_string()))?,
+            }),
+            ("POST",   ["volume"])           => Route::Control(Request::Volume {
+                volume: value("volume")?.parse().map_err(|_| (400, "Invalid volume.".to_string()))?,
+            }),
+            ("GET",    ["library"])          => Route::Library,
+            ("GET",    ["library", a, b])    => Route::Album(index(a)?, index(b)?),
+            ("GET",    ["search"])           => Route::Search(value("query")?.clone()),
+            ("GET",    ["queue"])            => Route::Queue,
+            ("POST",   ["queue"])            => {
+                let body: QueueAdd = serde_json::from_slice(&request.body).map_err(|error| (400, error.to_string()))?;
+                Route::QueueAdd(body.list, body.next)
+            }
+            ("POST",   ["queue", i])         => Route::QueuePlay(index(i)?),
+            ("DELETE", ["queue"])            => Route::QueueClear,
+            ("DELETE", ["queue", i])         => Route::QueueRemove(index(i)?),
+            _ => return Err((404, "Unknown route.".to_string())),
+        };
+
+        Ok(route)
+    }
+
+    /// Check the remote token of a request, either as a bearer token or a "token" query parameter.
+    fn is_allowed(request: &HttpRequest, query: &HashMap<String, String>, token: &str) -> bool {
+        let bearer = Self::get_header(request, "Authorization");
+        let bearer = bearer.and_then(|x| x.strip_prefix("Bearer "));
+
+        bearer.is_some_and(|x| Self::is_token(x, token))
+            | query.get("token").is_some_and(|x| Self::is_token(x, token))
+    }
+
+    /// Compare a token in constant time, so that the remote token can't be guessed by response time.
+    fn is_token(text: &str, token: &str) -> bool {
+        text.len() == token.len()
+            && text
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |value, (a, b)| value | (a ^ b))
+                == 0
+    }
+
+    fn get_header<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+        request
+            .list_header
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, x)| x.as_str())
+    }
+
+    fn respond(stream: &mut TcpStream, code: u16, body: Value) {
+        let body = body.to_string();
+        let reason = StatusCode::from_u16(code)
+            .ok()
+            .and_then(|x| x.canonical_reason())
+            .unwrap_or_default();
+        let response = format!(
+            "HTTP/1.1 {code} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+
+        let _ = stream.write_all(response.as_bytes());
+    }
+
+    /// Split a URL into the path and every query parameter, decoded.
+    fn get_query(url: &str) -> (&str, HashMap<String, String>) {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let query = query
+            .split('&')
+            .filter_map(|x| x.split_once('='))
+            .map(|(key, value)| (Self::decode(key), Self::decode(value)))
+            .collect();
+
+        (path, query)
+    }
+
+    /// Decode a URL query component.
+    fn decode(text: &str) -> String {
+        let text = text.replace('+', " ");
+        let byte = text.as_bytes();
+        let mut data = Vec::new();
+        let mut i = 0;
+
+        while i < byte.len() {
+            if byte[i] == b'%'
+                && let Some(code) = text.get(i + 1..i + 3)
+                && let Ok(code) = u8::from_str_radix(code, 16)
+            {
+                data.push(code);
+                i += 3;
+            } else {
+                data.push(byte[i]);
+                i += 1;
+            }
+        }
+
+        String::from_utf8_lossy(&data).to_string()
+    }
+}
+
+//================================================================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_request(
+        method: &str,
+        url: &str,
+        list_header: &[(&str, &str)],
+        body: &str,
+    ) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            list_header: list_header
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn get_route(method: &str, url: &str, body: &str) -> Result<Route, (u16, String)> {
+        let request = get_request(method, url, &[], body);
+        let (path, query) = Remote::get_query(url);
+
+        Remote::get_route(&request, path, &query)
+    }
+
+    fn is_allowed(url: &str, bearer: Option<&str>) -> bool {
+        let list_header: Vec<_> = bearer.map(|x| ("authorization", x)).into_iter().collect();
+        let request = get_request("GET", url, &list_header, "");
+        let (_, query) = Remote::get_query(url);
+
+        Remote::is_allowed(&request, &query, "0123abcd")
+    }
+
+    /// a reader that gives its data a few bytes at a time, like a slow client.
+    struct Slow<'a>(&'a [u8]);
+
+    impl Read for Slow<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            let size = self.0.len().min(buffer.len()).min(3);
+            buffer[..size].copy_from_slice(&self.0[..size]);
+            self.0 = &self.0[size..];
+            Ok(size)
+        }
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(Remote::decode("a%20b+c"), "a b c");
+        assert_eq!(Remote::decode("%C3%A9t%C3%A9"), "été");
+        assert_eq!(Remote::decode("100%"), "100%");
+        assert_eq!(Remote::decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn read_request() {
+        let data =
+            b"POST /queue?token=a HTTP/1.1\r\nHost: x\r\ncontent-length: 5\r\n\r\n[0,1]extra";
+        let (request, rest) = Remote::read_request(&mut &data[..]).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "/queue?token=a");
+        assert_eq!(Remote::get_header(&request, "Host"), Some("x"));
+        assert_eq!(request.body, b"[0,1]");
+        assert_eq!(rest, b"extra");
+
+        // a request sent a few bytes at a time is read up to the end of the body.
+        let (request, _) = Remote::read_request(&mut Slow(data)).unwrap();
+
+        assert_eq!(request.body, b"[0,1]");
+
+        let data = b"GET /status HTTP/1.1\r\n\r\n";
+        let (request, rest) = Remote::read_request(&mut Slow(data)).unwrap();
+
+        assert!(request.body.is_empty());
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn read_request_error() {
+        let read = |data: &[u8]| Remote::read_request(&mut Slow(data)).err().map(|x| x.0);
+
+        assert_eq!(read(b"GET /status HTTP/1.1\r\n"), Some(400));
+        assert_eq!(read(b"GET /status\0 HTTP/1.1\r\n\r\n"), Some(400));
+        assert_eq!(
+            read(b"POST /queue HTTP/1.1\r\nContent-Length: 9\r\n\r\n[0]"),
+            Some(400)
+        );
+        assert_eq!(
+            read(b"POST /queue HTTP/1.1\r\nContent-Length: x\r\n\r\n"),
+            Some(400)
+        );
+        assert_eq!(
+            read(b"POST /queue HTTP/1.1\r\nContent-Length: 99999999\r\n\r\n"),
+            Some(413)
+        );
+        assert_eq!(
+            read(b"POST /queue HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            Some(411)
+        );
+        assert_eq!(read(&[b'a'; 32 * 1024]), Some(431));
+    }
+
+    #[test]
+    fn route() {
+        assert!(matches!(
+            get_route("GET", "/status", ""),
+            Ok(Route::Control(Request::Status))
+        ));
+        assert!(matches!(
+            get_route("POST", "/quit", ""),
+            Ok(Route::Control(Request::Quit))
+        ));
+        assert!(matches!(
+            get_route("POST", "/seek?time=90", ""),
+            Ok(Route::Control(Request::Seek { time: 90 }))
+        ));
+        assert!(matches!(
+            get_route("GET", "/library/1/2", ""),
+            Ok(Route::Album(1, 2))
+        ));
+        assert!(matches!(
+            get_route("GET", "/search?query=genre%3Drock", ""),
+            Ok(Route::Search(query)) if query == "genre=rock"
+        ));
+        assert!(matches!(
+            get_route("POST", "/queue", r#"{"list": [[0, 1, 2]], "next": true}"#),
+            Ok(Route::QueueAdd(list, true)) if list == [(0, 1, 2)]
+        ));
+        assert!(matches!(
+            get_route("DELETE", "/queue/3", ""),
+            Ok(Route::QueueRemove(3))
+        ));
+        assert!(matches!(
+            get_route("DELETE", "/queue", ""),
+            Ok(Route::QueueClear)
+        ));
+    }
+
+    #[test]
+    fn route_error() {
+        assert!(matches!(get_route("POST", "/seek", ""), Err((400, _))));
+        assert!(matches!(
+            get_route("POST", "/seek?time=x", ""),
+            Err((400, _))
+        ));
+        assert!(matches!(
+            get_route("GET", "/library/x/1", ""),
+            Err((400, _))
+        ));
+        assert!(matches!(
+            get_route("POST", "/queue", "[0, 1]"),
+            Err((400, _))
+        ));
+        assert!(matches!(get_route("GET", "/next", ""), Err((404, _))));
+        assert!(matches!(get_route("GET", "/unknown", ""), Err((404, _))));
+    }
+
+    #[test]
+    fn allow() {
+        assert!(is_allowed("/status", Some("Bearer 0123abcd")));
+        assert!(is_allowed("/status?token=0123abcd", None));
+        assert!(is_allowed("/status?token=wrong", Some("Bearer 0123abcd")));
+        assert!(!is_allowed("/status", None));
+        assert!(!is_allowed("/status", Some("Bearer 0123abce")));
+        assert!(!is_allowed("/status", Some("Bearer 0123abcd0")));
+        assert!(!is_allowed("/status", Some("0123abcd")));
+        assert!(!is_allowed("/status?token=", None));
+    }
+}
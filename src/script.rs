@@ -48,7 +48,7 @@
 * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
-use crate::{app::*, setting::*};
+use crate::{app::*, queue::*, search::*, setting::*};
 
 //================================================================
 
@@ -205,6 +205,7 @@ impl Script {
     pub const CALL_SKIP_A: &'static str = "skip_a";
     pub const CALL_SKIP_B: &'static str = "skip_b";
     pub const CALL_PAUSE: &'static str = "pause";
+    pub const CALL_QUEUE: &'static str = "queue";
 
     pub fn new(setting: &Setting) -> anyhow::Result<Self> {
         let lua = unsafe { Lua::unsafe_new() };
@@ -293,7 +294,12 @@ impl Script {
         melodix.set("get_library", lua.create_function(Self::get_library)?)?;
         melodix.set("get_state", lua.create_function(Self::get_state)?)?;
         melodix.set("get_queue", lua.create_function(Self::get_queue)?)?;
+        melodix.set("get_search", lua.create_function(Self::get_search)?)?;
         melodix.set("set_toast", lua.create_function(Self::set_toast)?)?;
+        melodix.set("get_repeat", lua.create_function(Self::get_repeat)?)?;
+        melodix.set("set_repeat", lua.create_function(Self::set_repeat)?)?;
+        melodix.set("get_stop", lua.create_function(Self::get_stop)?)?;
+        melodix.set("set_stop", lua.create_function(Self::set_stop)?)?;
 
         lua.globals().set("melodix", melodix)?;
 
@@ -323,16 +329,27 @@ impl Script {
     fn get_queue(lua: &Lua, _: ()) -> mlua::Result<(mlua::Value, mlua::Value)> {
         let app = App::dereference();
 
-        let queue = &app.window.queue.0;
+        let queue = &app.queue.list;
         let queue: Vec<(usize, usize, usize)> = queue
             .into_iter()
             .map(|(group, album, track)| (group + 1, album + 1, track + 1))
             .collect();
 
-        Ok((
-            lua.to_value(&queue)?,
-            lua.to_value(&(app.window.queue.1 + 1))?,
-        ))
+        Ok((lua.to_value(&queue)?, lua.to_value(&(app.queue.index + 1))?))
+    }
+
+    fn get_search(lua: &Lua, query: String) -> mlua::Result<mlua::Value> {
+        let app = App::dereference();
+
+        let search = Search::new(&query)
+            .map_err(|error| mlua::Error::runtime(format!("get_search(): {error}")))?;
+        let search: Vec<(usize, usize, usize)> = search
+            .run(&app.library)
+            .into_iter()
+            .map(|(group, album, track)| (group + 1, album + 1, track + 1))
+            .collect();
+
+        lua.to_value(&search)
     }
 
     fn set_toast(_: &Lua, (kind, text, time): (usize, String, f64)) -> mlua::Result<()> {
@@ -355,6 +372,52 @@ impl Script {
 
         Ok(())
     }
+
+    fn get_repeat(_: &Lua, _: ()) -> mlua::Result<usize> {
+        let app = App::dereference();
+
+        Ok(match app.queue.repeat {
+            Repeat::Off => 0,
+            Repeat::One => 1,
+            Repeat::All => 2,
+        })
+    }
+
+    fn set_repeat(_: &Lua, repeat: usize) -> mlua::Result<()> {
+        let app = App::dereference();
+
+        app.queue.repeat = match repeat {
+            0 => Repeat::Off,
+            1 => Repeat::One,
+            2 => Repeat::All,
+            _ => return Err(mlua::Error::runtime("set_repeat(): Invalid repeat kind.")),
+        };
+
+        Ok(())
+    }
+
+    fn get_stop(_: &Lua, _: ()) -> mlua::Result<usize> {
+        let app = App::dereference();
+
+        Ok(match app.queue.stop {
+            Stop::Off => 0,
+            Stop::Track => 1,
+            Stop::Album => 2,
+        })
+    }
+
+    fn set_stop(_: &Lua, stop: usize) -> mlua::Result<()> {
+        let app = App::dereference();
+
+        app.queue.stop = match stop {
+            0 => Stop::Off,
+            1 => Stop::Track,
+            2 => Stop::Album,
+            _ => return Err(mlua::Error::runtime("set_stop(): Invalid stop kind.")),
+        };
+
+        Ok(())
+    }
 }
 
 impl Drop for Script {
@@ -0,0 +1,373 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::library::*;
+
+//================================================================
+
+/// library search, from a query such as `miles blue` or `artist:radiohead year:>1997 genre:rock time:<5m`.
+pub struct Search {
+    /// every plain search token, folded (see Search::fold).
+    list_token: Vec<String>,
+    /// every field filter, i.e. `year:>1997`.
+    list_filter: Vec<Filter>,
+}
+
+enum Filter {
+    /// field contains the text, folded (see Search::fold).
+    Text(Field, String),
+    /// field compares to the value. the value is in seconds for the time field.
+    Number(Field, Order, u64),
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Group,
+    Album,
+    Track,
+    Genre,
+    Date,
+    Time,
+    Path,
+}
+
+#[derive(Clone, Copy)]
+enum Order {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+impl Search {
+    /// score for a token matching a field, for group, album, track, genre, date, path.
+    const SCORE_FIELD: [usize; 6] = [3, 3, 4, 1, 1, 1];
+    /// additional score for a token matching the start of a word.
+    const SCORE_START: usize = 1;
+    /// additional score for a token matching a whole word.
+    const SCORE_WHOLE: usize = 2;
+
+    /// Parse a search query. Will return an error describing any bad syntax.
+    pub fn new(text: &str) -> anyhow::Result<Self> {
+        let mut list_token = Vec::new();
+        let mut list_filter = Vec::new();
+
+        for token in Self::split(text)? {
+            // a field filter, i.e. `artist:radiohead`. a quoted token is always plain text.
+            if let Some((key, value)) = token.split_once(':')
+                && !key.is_empty()
+                && key.chars().all(|x| x.is_ascii_alphabetic())
+            {
+                let field = Self::get_field(key)?;
+
+                if value.is_empty() {
+                    return Err(anyhow::anyhow!("Missing value for \"{key}:\"."));
+                }
+
+                list_filter.extend(Self::get_filter(field, key, value)?);
+            } else {
+                list_token.push(Self::fold(&token.replace('"', "")));
+            }
+        }
+
+        Ok(Self {
+            list_token,
+            list_filter,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list_token.is_empty() && self.list_filter.is_empty()
+    }
+
+    /// Split a query by white-space, keeping any quoted text together.
+    fn split(text: &str) -> anyhow::Result<Vec<String>> {
+        let mut list = Vec::new();
+        let mut token = String::new();
+        let mut quote = false;
+
+        for letter in text.chars() {
+            match letter {
+                '"' => {
+                    quote = !quote;
+                    token.push(letter);
+                }
+                letter if letter.is_whitespace() && !quote => {
+                    if !token.is_empty() {
+                        list.push(std::mem::take(&mut token));
+                    }
+                }
+                letter => token.push(letter),
+            }
+        }
+
+        if quote {
+            return Err(anyhow::anyhow!("Missing closing quote."));
+        }
+
+        if !token.is_empty() {
+            list.push(token);
+        }
+
+        Ok(list)
+    }
+
+    fn get_field(key: &str) -> anyhow::Result<Field> {
+        match key.to_lowercase().as_str() {
+            "artist" | "group" => Ok(Field::Group),
+            "album" => Ok(Field::Album),
+            "title" | "track" => Ok(Field::Track),
+            "genre" => Ok(Field::Genre),
+            "year" | "date" => Ok(Field::Date),
+            "time" | "length" => Ok(Field::Time),
+            "path" => Ok(Field::Path),
+            _ => Err(anyhow::anyhow!(
+                "Unknown field \"{key}:\". Use artist, album, title, genre, year, time or path."
+            )),
+        }
+    }
+
+    fn get_filter(field: Field, key: &str, value: &str) -> anyhow::Result<Vec<Filter>> {
+        let value = value.replace('"', "");
+
+        if !matches!(field, Field::Date | Field::Time) {
+            return Ok(vec![Filter::Text(field, Self::fold(&value))]);
+        }
+
+        // a range, i.e. `year:1990..1999`.
+        if let Some((a, b)) = value.split_once("..") {
+            return Ok(vec![
+                Filter::Number(field, Order::GreaterEqual, Self::get_value(field, key, a)?),
+                Filter::Number(field, Order::LessEqual, Self::get_value(field, key, b)?),
+            ]);
+        }
+
+        let (order, value) = if let Some(value) = value.strip_prefix(">=") {
+            (Order::GreaterEqual, value)
+        } else if let Some(value) = value.strip_prefix("<=") {
+            (Order::LessEqual, value)
+        } else if let Some(value) = value.strip_prefix('>') {
+            (Order::Greater, value)
+        } else if let Some(value) = value.strip_prefix('<') {
+            (Order::Less, value)
+        } else {
+            (Order::Equal, value.strip_prefix('=').unwrap_or(&value))
+        };
+
+        Ok(vec![Filter::Number(
+            field,
+            order,
+            Self::get_value(field, key, value)?,
+        )])
+    }
+
+    /// Parse a year, or a time in seconds: `90`, `90s`, `5m`, `1h` or `3:30`.
+    fn get_value(field: Field, key: &str, value: &str) -> anyhow::Result<u64> {
+        let value = value.trim().to_lowercase();
+
+        let parse = match field {
+            Field::Time => {
+                if let Some((minute, second)) = value.split_once(':') {
+                    minute
+                        .parse::<u64>()
+                        .ok()
+                        .zip(second.parse::<u64>().ok())
+                        .map(|(minute, second)| minute * 60 + second)
+                } else if let Some(value) = value.strip_suffix('h') {
+                    value.parse::<u64>().ok().map(|x| x * 3600)
+                } else if let Some(value) = value.strip_suffix('m') {
+                    value.parse::<u64>().ok().map(|x| x * 60)
+                } else {
+                    value.strip_suffix('s').unwrap_or(&value).parse().ok()
+                }
+            }
+            _ => value.parse().ok(),
+        };
+
+        parse.ok_or_else(|| match field {
+            Field::Time => anyhow::anyhow!(
+                "Invalid time \"{value}\" for \"{key}:\". Use a time such as 90s, 5m, 1h or 3:30."
+            ),
+            _ => {
+                anyhow::anyhow!("Invalid year \"{value}\" for \"{key}:\". Use a year such as 1997.")
+            }
+        })
+    }
+
+    /// Get every track in the library matching the search, as a group, album, track index. Better matches come first.
+    pub fn run(&self, library: &Library) -> Vec<(usize, usize, usize)> {
+        let mut list = Vec::new();
+
+        for (i_group, group) in library.list_group.iter().enumerate() {
+            for (i_album, album) in group.list_album.iter().enumerate() {
+                for (i_track, track) in album.list_track.iter().enumerate() {
+                    if let Some(score) = self.get_score(group, album, track) {
+                        list.push((score, (i_group, i_album, i_track)));
+                    }
+                }
+            }
+        }
+
+        // stable sort, so that equal matches keep the library order.
+        list.sort_by(|a, b| b.0.cmp(&a.0));
+
+        list.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// Check if a track passes every field filter.
+    pub fn check(&self, group: &Group, album: &Album, track: &Track) -> bool {
+        self.list_filter.iter().all(|filter| match filter {
+            Filter::Text(field, text) => {
+                let value = match field {
+                    Field::Group => &group.name,
+                    Field::Album => &album.name,
+                    Field::Track => &track.name,
+                    Field::Genre => track.kind.as_deref().unwrap_or_default(),
+                    Field::Path => &track.path,
+                    _ => return false,
+                };
+
+                Self::fold(value).contains(text.as_str())
+            }
+            Filter::Number(field, order, value) => {
+                let field = match field {
+                    Field::Date => track.get_year().map(u64::from),
+                    Field::Time => Some(track.time.as_secs()),
+                    _ => None,
+                };
+
+                field.is_some_and(|field| match order {
+                    Order::Less => field < *value,
+                    Order::LessEqual => field <= *value,
+                    Order::Equal => field == *value,
+                    Order::GreaterEqual => field >= *value,
+                    Order::Greater => field > *value,
+                })
+            }
+        })
+    }
+
+    /// Get the score of a track, if it passes every field filter and every token matches at least one field.
+    pub fn get_score(&self, group: &Group, album: &Album, track: &Track) -> Option<usize> {
+        if !self.check(group, album, track) {
+            return None;
+        }
+
+        let field = [
+            Self::fold(&group.name),
+            Self::fold(&album.name),
+            Self::fold(&track.name),
+            Self::fold(track.kind.as_deref().unwrap_or_default()),
+            Self::fold(track.date.as_deref().unwrap_or_default()),
+            Self::fold(&track.path),
+        ];
+
+        let mut score = 0;
+
+        for token in &self.list_token {
+            let mut hit = false;
+
+            for (text, weight) in field.iter().zip(Self::SCORE_FIELD) {
+                if !text.contains(token.as_str()) {
+                    continue;
+                }
+
+                hit = true;
+                score += weight;
+
+                let mut word = text.split(|x: char| !x.is_alphanumeric());
+
+                if word.clone().any(|x| x == token) {
+                    score += Self::SCORE_WHOLE;
+                } else if word.any(|x| x.starts_with(token.as_str())) {
+                    score += Self::SCORE_START;
+                }
+            }
+
+            if !hit {
+                return None;
+            }
+        }
+
+        Some(score)
+    }
+
+    /// Fold text for comparison: lower-case, and strip any accent from a Latin letter (i.e. "Beyoncé" becomes "beyonce").
+    pub fn fold(text: &str) -> String {
+        let mut fold = String::with_capacity(text.len());
+
+        for letter in text.chars().flat_map(char::to_lowercase) {
+            match letter {
+                'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => fold.push('a'),
+                'ç' | 'ć' | 'č' => fold.push('c'),
+                'ď' | 'đ' => fold.push('d'),
+                'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => fold.push('e'),
+                'ğ' => fold.push('g'),
+                'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => fold.push('i'),
+                'ł' => fold.push('l'),
+                'ñ' | 'ń' | 'ň' => fold.push('n'),
+                'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => fold.push('o'),
+                'ŕ' | 'ř' => fold.push('r'),
+                'ś' | 'š' | 'ş' => fold.push('s'),
+                'ť' | 'ţ' => fold.push('t'),
+                'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => fold.push('u'),
+                'ý' | 'ÿ' => fold.push('y'),
+                'ź' | 'ż' | 'ž' => fold.push('z'),
+                'ß' => fold.push_str("ss"),
+                'æ' => fold.push_str("ae"),
+                'œ' => fold.push_str("oe"),
+                letter => fold.push(letter),
+            }
+        }
+
+        fold
+    }
+}
@@ -48,11 +48,11 @@
 * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
-use crate::app::*;
+use crate::{library::*, store::*};
 
 //================================================================
 
-use eframe::{CreationContext, egui};
+use eframe::egui;
 use serde::{Deserialize, Serialize};
 
 //================================================================
@@ -69,25 +69,59 @@ pub struct Setting {
     pub window_kind: bool,
     pub window_track: bool,
     pub script_allow: bool,
+    pub bookmark_time: u64,
+    pub bookmark_kind: bool,
+    pub chapter_skip: bool,
+    pub playlist_relative: bool,
+    /// last scanned library folder, for a re-scan.
+    pub library_path: Option<String>,
+    /// run the remote API server.
+    pub remote_allow: bool,
+    /// remote API address to bind to.
+    pub remote_address: String,
+    /// remote API token, for every request.
+    pub remote_token: String,
+    /// run the MPD server.
+    pub mpd_allow: bool,
+    /// MPD server address to bind to.
+    pub mpd_address: String,
+    /// track table sort state, for the sort column and descending order. none for album order.
+    pub sort_track: Option<(Sort, bool)>,
+    /// queue table sort state, for the sort column and descending order. none for queue order.
+    pub sort_queue: Option<(Sort, bool)>,
+}
+
+/// setting layout from before there was a layout version, for migration.
+#[derive(Deserialize)]
+struct SettingLegacy {
+    window_scale: f32,
+    window_theme: bool,
+    window_media: bool,
+    window_tray: bool,
+    window_push: bool,
+    window_time: bool,
+    window_date: bool,
+    window_kind: bool,
+    window_track: bool,
+    script_allow: bool,
 }
 
 impl Setting {
-    const PATH_SETTING: &'static str = "setting.data";
+    /// Make a new random remote API token.
+    pub fn make_token() -> String {
+        format!("{:032x}", rand::random::<u128>())
+    }
 
-    pub fn new(context: &CreationContext) -> Self {
-        if let Ok(file) = std::fs::read(App::get_configuration_path(Self::PATH_SETTING, false)) {
-            if let Ok(setting) = postcard::from_bytes::<Self>(&file) {
-                context.egui_ctx.set_zoom_factor(setting.window_scale);
+    pub fn new(context: &egui::Context) -> Self {
+        let setting = Self::load();
 
-                if setting.window_theme {
-                    context.egui_ctx.set_theme(egui::Theme::Light);
-                }
+        context.set_zoom_factor(setting.window_scale);
 
-                return setting;
-            }
+        if setting.window_theme {
+            context.set_theme(egui::Theme::Light);
         }
 
-        Self::default()
+        setting
     }
 }
 
@@ -104,13 +138,56 @@ impl Default for Setting {
             window_kind: true,
             window_track: true,
             script_allow: true,
+            bookmark_time: 20,
+            bookmark_kind: true,
+            chapter_skip: false,
+            playlist_relative: false,
+            library_path: None,
+            remote_allow: false,
+            remote_address: "127.0.0.1:8090".to_string(),
+            remote_token: Self::make_token(),
+            mpd_allow: false,
+            mpd_address: "127.0.0.1:6600".to_string(),
+            sort_track: None,
+            sort_queue: None,
+        }
+    }
+}
+
+impl Store for Setting {
+    const PATH: &'static str = "setting.data";
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, data: &[u8]) -> Option<Self> {
+        if version != 0 {
+            return None;
+        }
+
+        if let Some(setting) = decode(data) {
+            return Some(setting);
         }
+
+        let legacy: SettingLegacy = decode(data)?;
+
+        let mut setting = Self::default();
+
+        setting.window_scale = legacy.window_scale;
+        setting.window_theme = legacy.window_theme;
+        setting.window_media = legacy.window_media;
+        setting.window_tray = legacy.window_tray;
+        setting.window_push = legacy.window_push;
+        setting.window_time = legacy.window_time;
+        setting.window_date = legacy.window_date;
+        setting.window_kind = legacy.window_kind;
+        setting.window_track = legacy.window_track;
+        setting.script_allow = legacy.script_allow;
+
+        Some(setting)
     }
 }
 
 impl Drop for Setting {
     fn drop(&mut self) {
-        let serialize: Vec<u8> = postcard::to_allocvec(&self).unwrap();
-        std::fs::write(App::get_configuration_path(Self::PATH_SETTING, false), serialize).unwrap();
+        self.save();
     }
 }
@@ -0,0 +1,134 @@
+/*
+* Copyright (c) 2025 luxreduxdelux
+*
+* Redistribution and use in source and binary forms, with or without
+* modification, are permitted provided that the following conditions are met:
+*
+* 1. Redistributions of source code must retain the above copyright notice,
+* this list of conditions and the following disclaimer.
+*
+* 2. Redistributions in binary form must reproduce the above copyright notice,
+* this list of conditions and the following disclaimer in the documentation
+* and/or other materials provided with the distribution.
+*
+* Subject to the terms and conditions of this license, each copyright holder
+* and contributor hereby grants to those receiving rights under this license
+* a perpetual, worldwide, non-exclusive, no-charge, royalty-free, irrevocable
+* (except for failure to satisfy the conditions of this license) patent license
+* to make, have made, use, offer to sell, sell, import, and otherwise transfer
+* this software, where such license applies only to those patent claims, already
+* acquired or hereafter acquired, licensable by such copyright holder or
+* contributor that are necessarily infringed by:
+*
+* (a) their Contribution(s) (the licensed copyrights of copyright holders and
+* non-copyrightable additions of contributors, in source or binary form) alone;
+* or
+*
+* (b) combination of their Contribution(s) with the work of authorship to which
+* such Contribution(s) was added by such copyright holder or contributor, if,
+* at the time the Contribution is added, such addition causes such combination
+* to be necessarily infringed. The patent license shall not apply to any other
+* combinations which include the Contribution.
+*
+* Except as expressly stated above, no rights or licenses from any copyright
+* holder or contributor is granted under this license, whether expressly, by
+* implication, estoppel or otherwise.
+*
+* DISCLAIMER
+*
+* THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+* AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+* IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+* DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDERS OR CONTRIBUTORS BE LIABLE
+* FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+* DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+* SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+* CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+* OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+* OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+*/
+
+use crate::app::*;
+
+//================================================================
+
+use serde::{Serialize, de::DeserializeOwned};
+
+//================================================================
+
+/// store file header, before the layout version.
+const MAGIC: &[u8; 4] = b"MDX\0";
+
+/// A store that is saved to the configuration folder, i.e. the setting or the library. Every store file has a layout version, so that an older layout can be migrated, rather than silently discarded.
+pub trait Store: Serialize + DeserializeOwned + Default {
+    /// store file name, in the configuration folder.
+    const PATH: &'static str;
+    /// store layout version. must be increased on every layout change, with a migration from the older layout.
+    const VERSION: u32;
+
+    /// Read an older store layout. Version 0 is a store file from before there was a layout version, which may already be in the current layout.
+    fn migrate(version: u32, data: &[u8]) -> Option<Self> {
+        match version {
+            0 => decode(data),
+            _ => None,
+        }
+    }
+
+    /// Load the store. An unreadable store file is kept as a back-up, and the default store is used instead.
+    fn load() -> Self {
+        let path = App::get_configuration_path(Self::PATH, false);
+
+        let Ok(file) = std::fs::read(&path) else {
+            return Self::default();
+        };
+
+        let store = match file.strip_prefix(MAGIC) {
+            Some(data) if data.len() >= 4 => {
+                let (version, data) = data.split_at(4);
+                let version = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+
+                if version == Self::VERSION {
+                    decode(data)
+                } else {
+                    Self::migrate(version, data)
+                }
+            }
+            _ => Self::migrate(0, &file),
+        };
+
+        store.unwrap_or_else(|| {
+            // keep the store file, or else it would be overwritten on the next save.
+            let backup = format!("{path}.old");
+            let _ = std::fs::rename(&path, &backup);
+
+            App::error(&format!(
+                "Couldn't read \"{path}\", so it has been reset. The old file was kept as \"{backup}\"."
+            ));
+
+            Self::default()
+        })
+    }
+
+    fn save(&self) {
+        let path = App::get_configuration_path(Self::PATH, false);
+        let mut data = MAGIC.to_vec();
+
+        data.extend(Self::VERSION.to_le_bytes());
+
+        let result = postcard::to_extend(self, data)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| std::fs::write(&path, data).map_err(anyhow::Error::from));
+
+        if let Err(error) = result {
+            App::error(&format!("Couldn't save \"{path}\": {error}"));
+        }
+    }
+}
+
+/// Decode a store layout. Every byte must be used, so that a different layout will not be mistaken for this one.
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Option<T> {
+    match postcard::take_from_bytes(data) {
+        Ok((value, [])) => Some(value),
+        _ => None,
+    }
+}
@@ -49,7 +49,10 @@
 */
 
 use crate::egui::ViewportCommand;
-use crate::{app::*, library::*, setting::*};
+use crate::{
+    app::*, argument::*, control::*, instance::*, library::*, mpd::*, player::*, playlist::*,
+    queue::*, remote::*, setting::*,
+};
 
 //================================================================
 
@@ -65,8 +68,17 @@ use notify_rust::Notification;
 use raw_window_handle::HasWindowHandle;
 
 use rodio::{OutputStream, Sink};
-use souvlaki::{MediaControlEvent, MediaControls, PlatformConfig};
-use std::sync::mpsc::{Receiver, Sender};
+use souvlaki::{
+    MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
+};
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender},
+    },
+    time::SystemTime,
+};
 use tray_icon::{
     TrayIconBuilder,
     menu::{MenuEvent, MenuItemBuilder},
@@ -74,16 +86,45 @@ use tray_icon::{
 
 //================================================================
 
+/// An event for the app to handle, from a multi-media key, push notification or tray menu.
+pub enum Event {
+    Media(MediaControlEvent),
+    /// set the repeat mode. cycle to the next repeat mode if none.
+    Repeat(Option<Repeat>),
+    /// set the stop mode. will toggle the stop mode off if it's already set.
+    Stop(Stop),
+    /// skip to the previous (false) or next (true) track, without skipping by chapter.
+    Skip(bool),
+    /// command-line argument, forwarded from another instance.
+    Argument(Argument),
+    /// control request, from the control socket.
+    Control(Message),
+    /// remote API route, from the remote API server.
+    Remote(Call),
+    /// MPD command, from the MPD server.
+    Mpd(Query),
+}
+
 #[allow(dead_code)]
 pub struct System {
-    /// media sink, for audio play-back.
-    pub sink: Sink,
-    /// multi-media key event handler.
-    pub media: Option<(MediaControls, Receiver<MediaControlEvent>)>,
+    /// media sink, for audio play-back. shared with the player thread.
+    pub sink: Arc<Sink>,
+    /// play-back event handler.
+    pub player: Player,
+    /// multi-media key event handler. the media controls are behind a cell, as every play-back update will publish to it.
+    pub media: Option<(RefCell<MediaControls>, Receiver<MediaControlEvent>)>,
     /// push event handler.
     push: Option<(Sender<String>, Receiver<String>)>,
     /// tray event handler.
     pub tray: Option<Receiver<MenuEvent>>,
+    /// single-instance lock, and forwarded argument handler.
+    instance: (Instance, Option<Receiver<Argument>>),
+    /// control server, and control request handler.
+    control: Option<(Control, Receiver<Message>)>,
+    /// remote API server, and remote API route handler.
+    remote: Option<(Remote, Receiver<Call>)>,
+    /// MPD server, and MPD command handler.
+    mpd: Option<(Mpd, Receiver<Query>)>,
     /// media sink stream and handle.
     stream: OutputStream,
     show: bool,
@@ -96,24 +137,41 @@ impl System {
     const TRAY_COMMAND_TOGGLE: &str = "2";
     const TRAY_COMMAND_SKIP_A: &str = "3";
     const TRAY_COMMAND_SKIP_B: &str = "4";
-    const TRAY_COMMAND_EXIT: &str = "5";
+    const TRAY_COMMAND_REPEAT: &str = "5";
+    const TRAY_COMMAND_STOP: &str = "6";
+    const TRAY_COMMAND_STOP_ALBUM: &str = "7";
+    const TRAY_COMMAND_EXIT: &str = "8";
     const PUSH_COMMAND_SKIP_A: &str = "skip_a";
     const PUSH_COMMAND_SKIP_B: &str = "skip_b";
-
-    pub fn new(setting: &Setting, context: &CreationContext) -> anyhow::Result<Self> {
+    /// cover cache folder, for every embedded track cover.
+    const PATH_COVER: &str = "cover/";
+    /// cover cache size. the least recently used cover is removed past it.
+    const COVER_LIMIT: usize = 64;
+
+    /// Create every system handler. The window is none if running headless, in which case there is no tray icon.
+    pub fn new(
+        setting: &Setting,
+        context: &egui::Context,
+        window: Option<&CreationContext>,
+        instance: Instance,
+    ) -> anyhow::Result<Self> {
         let stream = rodio::OutputStreamBuilder::open_default_stream()?;
-        let sink = rodio::Sink::connect_new(stream.mixer());
+        let sink = Arc::new(rodio::Sink::connect_new(stream.mixer()));
+        let player = Player::new(&sink, context.clone());
 
         #[cfg(target_os = "linux")]
         let hwnd = None;
 
         #[cfg(not(target_os = "linux"))]
-        let hwnd = {
-            let mut handle = context.window_handle()?;
+        let hwnd = match window {
+            Some(window) => {
+                let mut handle = window.window_handle()?;
 
-            let handle_pointer: *mut std::ffi::c_void =
-                &mut handle as *mut _ as *mut std::ffi::c_void;
-            Some(handle_pointer)
+                let handle_pointer: *mut std::ffi::c_void =
+                    &mut handle as *mut _ as *mut std::ffi::c_void;
+                Some(handle_pointer)
+            }
+            None => None,
         };
 
         let config = PlatformConfig {
@@ -122,10 +180,11 @@ impl System {
             hwnd,
         };
 
-        let media = if setting.window_media {
+        // media controls need a window handle, except for MPRIS.
+        let media = if setting.window_media && (cfg!(target_os = "linux") || window.is_some()) {
             let mut media = MediaControls::new(config)?;
 
-            let clone = context.egui_ctx.clone();
+            let clone = context.clone();
             let (event_tx, media_rx) = std::sync::mpsc::channel();
 
             media.attach(move |event: MediaControlEvent| {
@@ -134,12 +193,12 @@ impl System {
                     .send(event)
                     .expect("System::new(): Couldn't send media event.");
             })?;
-            Some((media, media_rx))
+            Some((RefCell::new(media), media_rx))
         } else {
             None
         };
 
-        let tray = if setting.window_tray {
+        let tray = if setting.window_tray && window.is_some() {
             #[cfg(target_os = "linux")]
             {
                 std::thread::spawn(|| {
@@ -156,6 +215,15 @@ impl System {
                             .build(),
                         &MenuItemBuilder::new().text("Skip -").enabled(true).build(),
                         &MenuItemBuilder::new().text("Skip +").enabled(true).build(),
+                        &MenuItemBuilder::new().text("Repeat").enabled(true).build(),
+                        &MenuItemBuilder::new()
+                            .text("Stop after current track")
+                            .enabled(true)
+                            .build(),
+                        &MenuItemBuilder::new()
+                            .text("Stop after current album")
+                            .enabled(true)
+                            .build(),
                         &MenuItemBuilder::new().text("Exit").enabled(true).build(),
                     ])
                     .expect("System::new(): Couldn't create tray menu.");
@@ -189,6 +257,15 @@ impl System {
                         .build(),
                     &MenuItemBuilder::new().text("Skip -").enabled(true).build(),
                     &MenuItemBuilder::new().text("Skip +").enabled(true).build(),
+                    &MenuItemBuilder::new().text("Repeat").enabled(true).build(),
+                    &MenuItemBuilder::new()
+                        .text("Stop after current track")
+                        .enabled(true)
+                        .build(),
+                    &MenuItemBuilder::new()
+                        .text("Stop after current album")
+                        .enabled(true)
+                        .build(),
                     &MenuItemBuilder::new().text("Exit").enabled(true).build(),
                 ])
                 .expect("System::new(): Couldn't create tray menu.");
@@ -208,7 +285,7 @@ impl System {
 
             let (tx, rx) = std::sync::mpsc::channel();
 
-            let clone = context.egui_ctx.clone();
+            let clone = context.clone();
             tray_icon::menu::MenuEvent::set_event_handler(Some(move |event| {
                 tx.send(event).unwrap();
                 clone.request_repaint();
@@ -225,12 +302,34 @@ impl System {
             None
         };
 
+        let instance_rx = instance.listen(context.clone());
+        let control = Control::new(context.clone(), &instance).unwrap_or_else(|error| {
+            App::error(&error.to_string());
+            None
+        });
+
+        // the remote API is optional, so don't fail start-up over it.
+        let remote = Remote::new(setting, context.clone()).unwrap_or_else(|error| {
+            App::error(&error.to_string());
+            None
+        });
+
+        let mpd = Mpd::new(setting, context.clone()).unwrap_or_else(|error| {
+            App::error(&error.to_string());
+            None
+        });
+
         Ok(Self {
             sink,
+            player,
             stream,
             media,
             push,
             tray,
+            instance: (instance, instance_rx),
+            control,
+            remote,
+            mpd,
             show: true,
             close: false,
         })
@@ -247,7 +346,11 @@ impl System {
     }
 
     pub fn toggle_visible(app: &mut App, context: &egui::Context) {
-        app.system.show = !app.system.show;
+        Self::set_visible(app, context, !app.system.show);
+    }
+
+    pub fn set_visible(app: &mut App, context: &egui::Context, show: bool) {
+        app.system.show = show;
 
         #[cfg(target_os = "linux")]
         {
@@ -263,12 +366,12 @@ impl System {
         context.send_viewport_cmd(ViewportCommand::Visible(app.system.show));
     }
 
-    pub fn poll_event(&mut self) -> Option<MediaControlEvent> {
+    pub fn poll_event(&mut self) -> Option<Event> {
         // if multi-media key event handler is present, try reading event.
         if let Some((_, media_rx)) = self.media.as_ref()
             && let Ok(event) = media_rx.try_recv()
         {
-            return Some(event);
+            return Some(Event::Media(event));
         }
 
         // if push notification event handler is present, try reading event.
@@ -276,22 +379,55 @@ impl System {
             && let Ok(event) = push_rx.try_recv()
         {
             match event.as_str() {
-                Self::PUSH_COMMAND_SKIP_A => return Some(MediaControlEvent::Previous),
-                Self::PUSH_COMMAND_SKIP_B => return Some(MediaControlEvent::Next),
+                Self::PUSH_COMMAND_SKIP_A => return Some(Event::Skip(false)),
+                Self::PUSH_COMMAND_SKIP_B => return Some(Event::Skip(true)),
                 _ => return None,
             }
         }
 
+        // if another instance has forwarded an argument, try reading it.
+        if let Some(instance_rx) = self.instance.1.as_ref()
+            && let Ok(argument) = instance_rx.try_recv()
+        {
+            return Some(Event::Argument(argument));
+        }
+
+        // if control server is present, try reading request.
+        if let Some((_, control_rx)) = self.control.as_ref()
+            && let Ok(message) = control_rx.try_recv()
+        {
+            return Some(Event::Control(message));
+        }
+
+        // if remote API server is present, try reading route.
+        if let Some((_, remote_rx)) = self.remote.as_ref()
+            && let Ok(call) = remote_rx.try_recv()
+        {
+            return Some(Event::Remote(call));
+        }
+
+        // if MPD server is present, try reading command.
+        if let Some((_, mpd_rx)) = self.mpd.as_ref()
+            && let Ok(query) = mpd_rx.try_recv()
+        {
+            return Some(Event::Mpd(query));
+        }
+
         // if tray notification event handler is present, try reading event.
         if let Some(tray_rx) = self.tray.as_ref()
             && let Ok(event) = tray_rx.try_recv()
         {
             match event.id.0.as_str() {
-                Self::TRAY_COMMAND_SHOW => return Some(MediaControlEvent::Raise),
-                Self::TRAY_COMMAND_TOGGLE => return Some(MediaControlEvent::Toggle),
-                Self::TRAY_COMMAND_SKIP_A => return Some(MediaControlEvent::Previous),
-                Self::TRAY_COMMAND_SKIP_B => return Some(MediaControlEvent::Next),
-                Self::TRAY_COMMAND_EXIT => return Some(MediaControlEvent::Quit),
+                Self::TRAY_COMMAND_SHOW => return Some(Event::Media(MediaControlEvent::Raise)),
+                Self::TRAY_COMMAND_TOGGLE => return Some(Event::Media(MediaControlEvent::Toggle)),
+                Self::TRAY_COMMAND_SKIP_A => {
+                    return Some(Event::Media(MediaControlEvent::Previous));
+                }
+                Self::TRAY_COMMAND_SKIP_B => return Some(Event::Media(MediaControlEvent::Next)),
+                Self::TRAY_COMMAND_REPEAT => return Some(Event::Repeat(None)),
+                Self::TRAY_COMMAND_STOP => return Some(Event::Stop(Stop::Track)),
+                Self::TRAY_COMMAND_STOP_ALBUM => return Some(Event::Stop(Stop::Album)),
+                Self::TRAY_COMMAND_EXIT => return Some(Event::Media(MediaControlEvent::Quit)),
                 _ => return None,
             }
         }
@@ -301,16 +437,57 @@ impl System {
 
     #[rustfmt::skip]
     pub fn make_event(
-        event: MediaControlEvent,
+        event: Event,
         app: &mut App,
         context: &egui::Context,
     ) -> anyhow::Result<()> {
+        let event = match event {
+            Event::Media(event) => event,
+            Event::Repeat(repeat) => {
+                app.queue.repeat = repeat.unwrap_or_else(|| app.queue.repeat.next());
+                return Ok(());
+            }
+            Event::Stop(stop) => {
+                // toggle the stop mode off if it's already set.
+                app.queue.stop = if app.queue.stop == stop { Stop::Off } else { stop };
+                return Ok(());
+            }
+            Event::Skip(false) => return app.track_skip_a(context),
+            Event::Skip(true)  => return app.track_skip_b(context),
+            Event::Argument(argument) => {
+                // a plain launch of another instance will just bring this one forward.
+                if argument.is_empty() {
+                    Self::set_visible(app, context, true);
+                    context.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+
+                return argument.apply(app, context);
+            }
+            Event::Control((request, response_tx)) => {
+                let error = Self::make_request(request, app, context).err().map(|x| x.to_string());
+                let _ = response_tx.send(Response::new(app, error));
+                return Ok(());
+            }
+            Event::Remote((route, response_tx)) => {
+                let response = match Remote::make_route(route, app, context) {
+                    Ok(value) => (200, value),
+                    Err(error) => (400, serde_json::json!({ "error": error.to_string() })),
+                };
+                let _ = response_tx.send(response);
+                return Ok(());
+            }
+            Event::Mpd((argument, response_tx)) => {
+                let _ = response_tx.send(Mpd::make_query(argument, app, context));
+                return Ok(());
+            }
+        };
+
         match event {
             MediaControlEvent::Play                 => app.track_play(),
             MediaControlEvent::Pause                => app.track_pause(),
             MediaControlEvent::Toggle               => app.track_toggle(),
-            MediaControlEvent::Next                 => app.track_skip_b(context)?,
-            MediaControlEvent::Previous             => app.track_skip_a(context)?,
+            MediaControlEvent::Next                 => app.chapter_skip_b(context)?,
+            MediaControlEvent::Previous             => app.chapter_skip_a(context)?,
             MediaControlEvent::Stop                 => app.track_stop(true),
             MediaControlEvent::Seek(seek_direction) => match seek_direction {
                 souvlaki::SeekDirection::Forward  => app.track_seek( 10, true),
@@ -337,6 +514,150 @@ impl System {
         Ok(())
     }
 
+    /// Publish the metadata of a track to the multi-media handler, if present. Will clear the metadata if there is no track.
+    pub fn set_metadata(&self, state: Option<(&Group, &Album, &Track)>) -> anyhow::Result<()> {
+        if let Some((media, _)) = self.media.as_ref() {
+            let mut media = media.borrow_mut();
+
+            if let Some((group, album, track)) = state {
+                let cover = Self::get_cover(album, track);
+
+                media.set_metadata(MediaMetadata {
+                    title: Some(&track.name),
+                    album: Some(&album.name),
+                    artist: Some(&group.name),
+                    cover_url: cover.as_deref(),
+                    duration: Some(track.time),
+                })?;
+            } else {
+                media.set_metadata(MediaMetadata::default())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publish the play-back status and position of the media sink to the multi-media handler, if present.
+    pub fn set_playback(&self) -> anyhow::Result<()> {
+        if let Some((media, _)) = self.media.as_ref() {
+            let progress = Some(MediaPosition(self.sink.get_pos()));
+
+            let playback = if self.sink.empty() {
+                MediaPlayback::Stopped
+            } else if self.sink.is_paused() {
+                MediaPlayback::Paused { progress }
+            } else {
+                MediaPlayback::Playing { progress }
+            };
+
+            // TO-DO publish the repeat mode as the MPRIS LoopStatus property (and take SetLoopStatus requests), once souvlaki exposes it. it owns the MPRIS object, so there is no way to add a property to it from here.
+            media.borrow_mut().set_playback(playback)?;
+        }
+
+        Ok(())
+    }
+
+    /// Publish the play state to every remote API WebSocket client and every idle MPD client, if either server is enabled.
+    pub fn publish(app: &App) {
+        if let Some((remote, _)) = app.system.remote.as_ref() {
+            remote.publish(&Response::new(app, None));
+        }
+
+        if let Some((mpd, _)) = app.system.mpd.as_ref() {
+            mpd.publish(app);
+        }
+    }
+
+    /// Publish the volume of the media sink to the multi-media handler, if present. Only MPRIS has a volume property.
+    pub fn set_volume(&self) -> anyhow::Result<()> {
+        #[cfg(target_os = "linux")]
+        if let Some((media, _)) = self.media.as_ref() {
+            media.borrow_mut().set_volume(self.sink.volume() as f64)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a file URL to the cover of a track, if any. An embedded cover is written to the cover cache first, keyed by a hash of the image.
+    fn get_cover(album: &Album, track: &Track) -> Option<String> {
+        if let Some(icon) = &track.icon.0 {
+            let extension = image::guess_format(icon)
+                .ok()
+                .and_then(|x| x.extensions_str().first().copied())
+                .unwrap_or("png");
+
+            let folder = App::get_configuration_path(Self::PATH_COVER, true);
+            let path = format!("{folder}{:016x}.{extension}", App::get_hash(icon));
+
+            let cover = if std::fs::exists(&path).unwrap_or_default() {
+                // mark the cover as recently used.
+                std::fs::File::options()
+                    .append(true)
+                    .open(&path)
+                    .and_then(|file| file.set_modified(SystemTime::now()))
+                    .is_ok()
+            } else {
+                let write = std::fs::write(&path, icon).is_ok();
+                Self::cover_evict(&folder);
+                write
+            };
+
+            if cover && let Ok(path) = std::path::absolute(&path) {
+                return Some(Playlist::url_encode(&path.display().to_string()));
+            }
+        }
+
+        album
+            .icon
+            .as_ref()
+            .and_then(|icon| std::path::absolute(icon).ok())
+            .map(|icon| Playlist::url_encode(&icon.display().to_string()))
+    }
+
+    /// Remove every least recently used cover past the cover cache size.
+    fn cover_evict(folder: &str) {
+        let Ok(list) = std::fs::read_dir(folder) else {
+            return;
+        };
+
+        let mut list: Vec<(SystemTime, std::path::PathBuf)> = list
+            .filter_map(Result::ok)
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+
+        if list.len() <= Self::COVER_LIMIT {
+            return;
+        }
+
+        list.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+
+        for (_, path) in list.into_iter().skip(Self::COVER_LIMIT) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Handle a control request, through the same path as every other event.
+    #[rustfmt::skip]
+    pub fn make_request(request: Request, app: &mut App, context: &egui::Context) -> anyhow::Result<()> {
+        match request {
+            Request::Play              => Self::make_event(Event::Media(MediaControlEvent::Play),     app, context)?,
+            Request::Pause             => Self::make_event(Event::Media(MediaControlEvent::Pause),    app, context)?,
+            Request::Toggle            => Self::make_event(Event::Media(MediaControlEvent::Toggle),   app, context)?,
+            Request::Next              => Self::make_event(Event::Skip(true),                         app, context)?,
+            Request::Previous          => Self::make_event(Event::Skip(false),                        app, context)?,
+            Request::Stop              => Self::make_event(Event::Media(MediaControlEvent::Stop),     app, context)?,
+            Request::Seek { time }     => app.track_seek(time as i64, false),
+            Request::Volume { volume } => app.track_set_volume(volume.clamp(0.0, 1.0)),
+            Request::Enqueue { path }  => {
+                Argument { path, enqueue: true, ..Default::default() }.apply(app, context)?
+            }
+            Request::Status            => {}
+            Request::Quit              => Self::make_event(Event::Media(MediaControlEvent::Quit),     app, context)?,
+        }
+
+        Ok(())
+    }
+
     #[rustfmt::skip]
     pub fn push_notification(&self, context: &egui::Context, state: (&Group, &Album, &Track)) -> anyhow::Result<()> {
         // if push notification event handler is present, send push notification.
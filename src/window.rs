@@ -48,28 +48,27 @@
 * OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 */
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::{app::*, library::*, script::*, system::*};
+use crate::{app::*, library::*, playlist::*, queue::*, script::*, search::*, system::*};
 
 //================================================================
 
 use eframe::egui::{self, Color32, Popup, Slider, TextureOptions, Vec2};
 use egui_extras::{Column, TableBuilder};
 use egui_toast::Toasts;
-use rand::seq::{IndexedRandom, SliceRandom};
 
 //================================================================
 
 pub struct Window {
     /// currently active layout (library, queue, etc.)
     pub layout: Layout,
-    /// repeat track.
-    pub repeat: bool,
-    /// randomize queue.
-    pub random: bool,
     /// search state, for group, album, track.
     pub search: (String, String, String),
+    /// browse state, for the browse mode of the left library panel, and the selected browse node index.
+    pub browse: (Browse, Option<usize>),
+    /// global search state, for the search query, every matching track (group, album, track) in rank order, and the query error, if any.
+    pub find: (String, Vec<(usize, usize, usize)>, Option<String>),
     /// select state, for group, album, track.
     /// index .0 is for the group/album/track index.
     /// index .1 is for the layout index (for adding a highlight to an entry in the window).
@@ -78,19 +77,31 @@ pub struct Window {
         (Option<usize>, Option<usize>),
         (Option<usize>, Option<usize>),
     ),
-    /// play state, for group, album, track.
-    pub state: Option<(usize, usize, usize)>,
-    /// queue state, for group, album, track, and queue index.
-    pub queue: (Vec<(usize, usize, usize)>, usize),
+    /// playlist import report, every playlist file entry that could not be found in the library.
+    pub unresolved: Vec<String>,
+    /// playlist state, for the selected playlist index in the playlist layout.
+    pub playlist: Option<usize>,
     /// toast notification list.
     pub toast: Toasts,
 }
 
+/// deferred folder layout action, for a list of entries.
+enum FolderAction {
+    /// play the list from an index in it.
+    Play(Vec<(usize, usize, usize)>, usize),
+    /// add the list to the queue, either after the current entry or at the end.
+    Queue(Vec<(usize, usize, usize)>, bool),
+    /// add the list to a playlist, by index.
+    Playlist(Vec<(usize, usize, usize)>, usize),
+}
+
 #[derive(PartialEq)]
 pub enum Layout {
     Welcome,
     Library,
+    Folder,
     Queue,
+    Playlist,
     Setup,
     About,
 }
@@ -123,12 +134,12 @@ impl Window {
             } else {
                 Layout::Library
             },
-            repeat: false,
-            random: false,
             search: (String::default(), String::default(), String::default()),
+            browse: (Browse::Group, None),
+            find: (String::default(), Vec::default(), None),
             select: ((None, None), (None, None), (None, None)),
-            state: None,
-            queue: (Vec::default(), 0),
+            unresolved: Vec::default(),
+            playlist: None,
             toast: Toasts::new()
                 .anchor(Align2::RIGHT_BOTTOM, (-8.0, -8.0))
                 .direction(egui::Direction::BottomUp),
@@ -136,20 +147,22 @@ impl Window {
     }
 
     pub fn draw(app: &mut App, context: &egui::Context) -> anyhow::Result<()> {
+        // play-back no longer depends on drawing, but keep a low-rate repaint while visible for any state that does not wake the app up (i.e. a toast's time-out, or a script change).
         context.request_repaint_after_secs(1.0);
 
-        app.script
-            .call_all(Script::CALL_TICK, app.system.sink.get_pos().as_secs());
-
         Self::handle_close(app, context);
-        Self::handle_track(app, context)?;
+        Self::handle_undo(app, context);
 
         app.window.toast.show(context);
 
+        Self::draw_unresolved(app, context);
+
         match app.window.layout {
             Layout::Welcome => Self::draw_welcome(app, context),
             Layout::Library => Self::draw_library(app, context),
+            Layout::Folder => Self::draw_folder(app, context),
             Layout::Queue => Self::draw_queue(app, context),
+            Layout::Playlist => Self::draw_playlist(app, context),
             Layout::Setup => Self::draw_setup(app, context),
             Layout::About => Self::draw_about(app, context),
         }
@@ -180,30 +193,23 @@ impl Window {
         }
     }
 
-    fn handle_track(app: &mut App, context: &egui::Context) -> anyhow::Result<()> {
-        if app.system.sink.empty()
-            && let Some(active) = app.window.state
-        {
-            if app.window.repeat {
-                app.track_add(active, context)?;
-            } else if app.window.random {
-                if app.window.queue.0.len() > 1 {
-                    let mut random: Vec<usize> = (0..app.window.queue.0.len()).collect();
-                    let mut picker = rand::rng();
-                    random.shuffle(&mut picker);
-
-                    let track = random.choose(&mut picker).unwrap();
-
-                    app.window.queue.1 = *track;
-                    app.track_add(*app.window.queue.0.get(*track).unwrap(), context)?;
-                }
-            } else if let Some(track) = app.window.queue.0.get(app.window.queue.1 + 1) {
-                app.window.queue.1 += 1;
-                app.track_add(*track, context)?;
-            }
+    fn handle_undo(app: &mut App, context: &egui::Context) {
+        use egui::{Key, KeyboardShortcut, Modifiers};
+
+        // text edit widget has focus, let it use the short-cut instead.
+        if context.wants_keyboard_input() {
+            return;
         }
 
-        Ok(())
+        let redo = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+        let undo = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+
+        // check for redo first, as the undo short-cut will also match with shift held down.
+        if context.input_mut(|i| i.consume_shortcut(&redo)) {
+            app.queue.redo();
+        } else if context.input_mut(|i| i.consume_shortcut(&undo)) {
+            app.queue.undo();
+        }
     }
 
     fn draw_button_image(
@@ -226,12 +232,62 @@ impl Window {
         )
     }
 
+    /// Draw the playlist import report, if any entry could not be found in the library.
+    fn draw_unresolved(app: &mut App, context: &egui::Context) {
+        if app.window.unresolved.is_empty() {
+            return;
+        }
+
+        let modal = egui::Modal::new(egui::Id::new("unresolved")).show(context, |ui| {
+            ui.heading("Unresolved Entries");
+            ui.label(format!(
+                "{} playlist entries could not be found in the library.",
+                app.window.unresolved.len()
+            ));
+
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .max_height(256.0)
+                .show(ui, |ui| {
+                    for entry in &app.window.unresolved {
+                        ui.label(entry);
+                    }
+                });
+
+            ui.separator();
+
+            ui.button("Close").clicked()
+        });
+
+        if modal.inner || modal.should_close() {
+            app.window.unresolved.clear();
+        }
+    }
+
+    /// Scan a folder into a new library, re-map the queue to it, and reset any window state referring to the old library.
+    pub fn library_scan(app: &mut App, folder: PathBuf) {
+        let folder = folder.as_path().display().to_string();
+
+        let library = std::mem::replace(&mut app.library, Library::scan(&folder));
+        app.setting.library_path = Some(folder);
+        // keep every queue entry still in the library.
+        Queue::rescan(app, &library);
+        app.window.find = (String::default(), Vec::default(), None);
+        app.window.browse = (Browse::Group, None);
+        app.window.select = ((None, None), (None, None), (None, None));
+        app.playlist.refresh();
+        app.window.layout = Layout::Library;
+    }
+
     // draw the top track status bar. hidden if no track is available.
     fn draw_panel_layout(app: &mut App, context: &egui::Context) {
         egui::TopBottomPanel::top("layout").show(context, |ui| {
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut app.window.layout, Layout::Library, "Library");
+                ui.selectable_value(&mut app.window.layout, Layout::Folder, "Folder");
                 ui.selectable_value(&mut app.window.layout, Layout::Queue, "Queue");
+                ui.selectable_value(&mut app.window.layout, Layout::Playlist, "Playlist");
                 ui.selectable_value(&mut app.window.layout, Layout::Setup, "Setup");
                 ui.selectable_value(&mut app.window.layout, Layout::About, "About");
             });
@@ -239,58 +295,130 @@ impl Window {
     }
 
     fn queue_save(app: &App, file: PathBuf) -> anyhow::Result<()> {
-        let mut file = std::fs::File::create(file)?;
-        let mut writer = m3u::Writer::new(&mut file);
+        let list: Vec<_> = app.queue.list.iter().map(|x| app.get_state(*x)).collect();
 
-        for entry in &app.window.queue.0 {
-            let (_, _, track) = app.get_state(*entry);
-            writer.write_entry(&m3u::Entry::Path(track.path.clone().into()))?;
+        Playlist::write(&file, &list, app.setting.playlist_relative)
+    }
+
+    pub fn queue_load(app: &mut App, context: &egui::Context, path: PathBuf) -> anyhow::Result<()> {
+        app.window.unresolved.clear();
+
+        let list = Self::queue_read(app, &path)?;
+
+        Queue::play(app, list, 0, context)
+    }
+
+    /// Read every entry from a playlist file that is in the library. Every other entry is added to the import report.
+    pub fn queue_read(app: &mut App, path: &Path) -> anyhow::Result<Vec<(usize, usize, usize)>> {
+        let mut list = Vec::new();
+
+        for entry in Playlist::read(path)? {
+            if let Some(track) = entry.resolve(&mut app.library) {
+                list.push(track);
+            } else {
+                app.window.unresolved.push(entry.get_label());
+            }
         }
 
-        Ok(())
+        Ok(list)
     }
 
-    fn queue_load(app: &mut App, context: &egui::Context, path: PathBuf) -> anyhow::Result<()> {
-        let mut reader = m3u::Reader::open(path)?;
-        let read_playlist: Vec<_> = reader.entries().map(|entry| entry.unwrap()).collect();
+    /// Get every track in a group, as a queue entry.
+    fn get_group_entry(app: &App, i_group: usize) -> Vec<(usize, usize, usize)> {
+        let group = app.library.list_group.get(i_group).unwrap();
+        let mut list = Vec::new();
+
+        for (i_album, album) in group.list_album.iter().enumerate() {
+            for i_track in 0..album.list_track.len() {
+                list.push((i_group, i_album, i_track));
+            }
+        }
+
+        list
+    }
 
-        Self::queue_reset(app);
+    /// Get every track in an album, as a queue entry.
+    fn get_album_entry(app: &App, i_group: usize, i_album: usize) -> Vec<(usize, usize, usize)> {
+        let group = app.library.list_group.get(i_group).unwrap();
+        let album = group.list_album.get(i_album).unwrap();
 
-        for entry in read_playlist {
-            match entry {
-                m3u::Entry::Path(path) => {
-                    let mut play = Vec::new();
+        (0..album.list_track.len())
+            .map(|i_track| (i_group, i_album, i_track))
+            .collect()
+    }
 
-                    for (i_group, group) in app.library.list_group.iter().enumerate() {
-                        for (i_album, album) in group.list_album.iter().enumerate() {
-                            for (i_track, track) in album.list_track.iter().enumerate() {
-                                if track.path == path.display().to_string() {
-                                    play.push((i_group, i_album, i_track));
-                                }
-                            }
-                        }
-                    }
+    /// Draw the "Play next" and "Add to queue" context menu entries. Returns true if the entry should be played next, false if it should be added to the end.
+    fn draw_queue_menu(ui: &mut egui::Ui) -> Option<bool> {
+        let next = ui.button("Play next").clicked();
+        let push = ui.button("Add to queue").clicked();
 
-                    for (i_g, i_a, i_t) in play {
-                        app.window.queue.0.push((i_g, i_a, i_t));
-                        app.window.queue.1 = 0;
-                    }
+        if next || push {
+            ui.close();
+            return Some(next);
+        }
 
-                    if let Some(first) = app.window.queue.0.first() {
-                        app.track_add(*first, context)?;
-                    }
+        None
+    }
+
+    /// Draw a sortable table column header. Clicking will cycle between an ascending, descending, and no sort.
+    fn draw_sort_header(
+        ui: &mut egui::Ui,
+        name: &str,
+        kind: Sort,
+        sort: &mut Option<(Sort, bool)>,
+    ) {
+        let text = match sort {
+            Some((x, false)) if *x == kind => format!("{name} ⬆"),
+            Some((x, true)) if *x == kind => format!("{name} ⬇"),
+            _ => name.to_string(),
+        };
+
+        let button = egui::Button::new(egui::RichText::new(text).strong()).frame(false);
+
+        if ui.add(button).clicked() {
+            *sort = match sort {
+                Some((x, false)) if *x == kind => Some((kind, true)),
+                Some((x, true)) if *x == kind => None,
+                _ => Some((kind, false)),
+            };
+        }
+    }
+
+    /// Draw the "Add to playlist" context menu entry. Returns the playlist index to add to, which will be past the end of the playlist list for a new playlist.
+    fn draw_playlist_menu(ui: &mut egui::Ui, playlist: &Playlist) -> Option<usize> {
+        let mut index = None;
+
+        ui.menu_button("Add to playlist", |ui| {
+            for (i, entry) in playlist.list_entry.iter().enumerate() {
+                // a smart playlist's track list is driven by its rule.
+                if entry.rule.is_some() {
+                    continue;
+                }
+
+                if ui.button(&entry.name).clicked() {
+                    index = Some(i);
                 }
-                _ => {}
             }
-        }
 
-        Ok(())
+            if playlist.list_entry.iter().any(|x| x.rule.is_none()) {
+                ui.separator();
+            }
+
+            if ui.button("New playlist").clicked() {
+                index = Some(playlist.list_entry.len());
+            }
+
+            if index.is_some() {
+                ui.close();
+            }
+        });
+
+        index
     }
 
-    fn queue_reset(app: &mut App) {
-        app.window.queue.0.clear();
-        app.window.queue.1 = 0;
-        app.track_stop(false);
+    /// Get the track key (see Track::get_key) of every queue entry in a list.
+    fn get_entry_key(app: &App, list: &[(usize, usize, usize)]) -> Vec<String> {
+        list.iter().map(|x| app.get_state(*x).2.get_key()).collect()
     }
 
     fn queue_play_group(
@@ -298,16 +426,9 @@ impl Window {
         i_group: usize,
         context: &egui::Context,
     ) -> anyhow::Result<()> {
-        Self::queue_reset(app);
-        let group = app.library.list_group.get(i_group).unwrap();
-
-        for (i_album, album) in group.list_album.iter().enumerate() {
-            for (i_track, _) in album.list_track.iter().enumerate() {
-                app.window.queue.0.push((i_group, i_album, i_track));
-            }
-        }
+        let list = Self::get_group_entry(app, i_group);
 
-        app.track_add((i_group, 0, 0), context)
+        Queue::play(app, list, 0, context)
     }
 
     fn queue_play_album(
@@ -316,33 +437,25 @@ impl Window {
         album: usize,
         context: &egui::Context,
     ) -> anyhow::Result<()> {
-        Self::queue_reset(app);
-        let i_group = app.library.list_group.get(group).unwrap();
-        let i_album = i_group.list_album.get(album).unwrap();
-
-        for x in 0..i_album.list_track.len() {
-            app.window.queue.0.push((group, album, x));
-        }
+        let list = Self::get_album_entry(app, group, album);
 
-        app.track_add((group, album, 0), context)
+        Queue::play(app, list, 0, context)
     }
 
     fn queue_play_track(
         app: &mut App,
         group: usize,
         album: usize,
-        track: usize,
+        list: Vec<usize>,
         context: &egui::Context,
     ) -> anyhow::Result<()> {
-        Self::queue_reset(app);
-        let i_group = app.library.list_group.get(group).unwrap();
-        let i_album = i_group.list_album.get(album).unwrap();
-
-        for x in track..i_album.list_track.len() {
-            app.window.queue.0.push((group, album, x));
+        if list.is_empty() {
+            return Ok(());
         }
 
-        app.track_add((group, album, track), context)
+        let list = list.into_iter().map(|x| (group, album, x)).collect();
+
+        Queue::play(app, list, 0, context)
     }
 
     //================================================================
@@ -357,76 +470,627 @@ impl Window {
         egui::CentralPanel::default().show(context, |ui| {
             ui.horizontal(|ui| {
                 if ui.button("Save Queue").clicked() {
-                    if let Some(file) = rfd::FileDialog::new().set_file_name("queue.m3u").add_filter("m3u", &["m3u"]).save_file() {
+                    if let Some(file) = rfd::FileDialog::new().set_file_name("queue.m3u8").add_filter("M3U", &["m3u8", "m3u"]).add_filter("PLS", &["pls"]).add_filter("XSPF", &["xspf"]).save_file() {
                         App::error_result(Self::queue_save(app, file));
                     }
                 }
                 if ui.button("Load Queue").clicked() {
-                    if let Some(file) = rfd::FileDialog::new().add_filter("m3u", &["m3u"]).pick_file() {
+                    if let Some(file) = rfd::FileDialog::new().add_filter("Playlist", &["m3u", "m3u8", "pls", "xspf"]).pick_file() {
                         App::error_result(Self::queue_load(app, context, file));
                     }
                 }
+
+                ui.separator();
+
+                if ui.button("Clear Played").clicked() {
+                    let list = app.queue.played();
+                    App::error_result(Queue::detach(app, &list, context));
+                }
+                if ui.button("Clear Queue").clicked() {
+                    Queue::reset(app);
+                }
+
+                ui.separator();
+
+                if ui.add_enabled(!app.queue.list_undo.is_empty(), egui::Button::new("Undo")).on_hover_text("Ctrl+Z").clicked() {
+                    app.queue.undo();
+                }
+                if ui.add_enabled(!app.queue.list_redo.is_empty(), egui::Button::new("Redo")).on_hover_text("Ctrl+Shift+Z").clicked() {
+                    app.queue.redo();
+                }
             });
 
             ui.separator();
 
             let table = TableBuilder::new(ui)
                 .striped(true)
-                .sense(egui::Sense::click())
+                .sense(egui::Sense::click_and_drag())
                 .column(Column::auto().resizable(true))
                 .column(Column::remainder().resizable(true).clip(true))
                 .column(Column::remainder().resizable(true).clip(true))
                 .column(Column::remainder().resizable(true).clip(true))
                 .column(Column::remainder().resizable(true).clip(true))
                 .header(16.0, |mut header| {
-                    header.col(|ui| { ui.strong("Number"); });
-                    header.col(|ui| { ui.strong("Group");  });
-                    header.col(|ui| { ui.strong("Album");  });
-                    header.col(|ui| { ui.strong("Track");  });
-                    header.col(|ui| { ui.strong("Time");   });
+                    let sort = &mut app.setting.sort_queue;
+
+                    header.col(|ui| { Self::draw_sort_header(ui, "Number", Sort::Number, sort); });
+                    header.col(|ui| { Self::draw_sort_header(ui, "Group",  Sort::Group,  sort); });
+                    header.col(|ui| { Self::draw_sort_header(ui, "Album",  Sort::Album,  sort); });
+                    header.col(|ui| { Self::draw_sort_header(ui, "Track",  Sort::Title,  sort); });
+                    header.col(|ui| { Self::draw_sort_header(ui, "Time",   Sort::Time,   sort); });
+                });
+
+            // display order, as a list of queue index.
+            let mut order: Vec<usize> = (0..app.queue.list.len()).collect();
+
+            if let Some((sort, descend)) = app.setting.sort_queue {
+                order.sort_by(|a, b| {
+                    // sort by number is sort by queue order.
+                    let compare = if sort == Sort::Number {
+                        a.cmp(b)
+                    } else {
+                        app.library.compare(app.queue.list[*a], app.queue.list[*b], sort).then(a.cmp(b))
+                    };
+
+                    if descend { compare.reverse() } else { compare }
                 });
+            }
 
             let mut detach = None;
+            let mut finish = None;
+            let mut shift = None;
+            let mut playlist = None;
+            let modifier = context.input(|i| i.modifiers);
 
             table.body(|ui| {
-                ui.rows(16.0, app.window.queue.0.len(), |mut row| {
-                    let index = row.index();
-                    let queue = app.window.queue.0.get(index).unwrap();
+                ui.rows(16.0, app.queue.list.len(), |mut row| {
+                    let position = row.index();
+                    let index = order[position];
+                    let queue = *app.queue.list.get(index).unwrap();
                     let group = app.library.list_group.get(queue.0).unwrap();
                     let album = group.list_album.get(queue.1).unwrap();
                     let track = album.list_track.get(queue.2).unwrap();
+                    let active = app.queue.state.is_some() && index == app.queue.index;
+
+                    // highlight the current entry, unless there's a selection.
+                    if app.queue.pick.is_empty() {
+                        row.set_selected(index == app.queue.index);
+                    } else {
+                        row.set_selected(app.queue.pick.contains(&index));
+                    }
 
-                    row.set_selected(index == app.window.queue.1);
+                    let number = if active { format!("▶ {}", index + 1) } else { (index + 1).to_string() };
 
-                    row.col(|ui| { ui.add(egui::Label::new((index + 1).to_string()).selectable(false));                });
+                    row.col(|ui| { ui.add(egui::Label::new(number).selectable(false));                                 });
                     row.col(|ui| { ui.add(egui::Label::new(&group.name).selectable(false));                            });
                     row.col(|ui| { ui.add(egui::Label::new(&album.name).selectable(false));                            });
                     row.col(|ui| { ui.add(egui::Label::new(&track.name).selectable(false));                            });
                     row.col(|ui| { ui.add(egui::Label::new(Self::format_time(track.time.as_secs() as usize)).selectable(false)); });
 
-                    row.response().context_menu(|ui| {
+                    let response = row.response();
+
+                    response.context_menu(|ui| {
                         if ui.button("Remove from queue").clicked() {
-                            detach = Some((index, index == app.window.queue.1));
+                            // remove every selected entry if this entry is part of the selection.
+                            if app.queue.pick.contains(&index) {
+                                detach = Some(app.queue.pick.clone());
+                            } else {
+                                detach = Some(vec![index]);
+                            }
+
+                            ui.close();
+                        }
+
+                        if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                            if app.queue.pick.contains(&index) {
+                                playlist = Some((app.queue.pick.clone(), target));
+                            } else {
+                                playlist = Some((vec![index], target));
+                            }
+                        }
+
+                        if app.bookmark.list_mark.contains_key(&track.get_key()) && ui.button("Mark as finished").clicked() {
+                            finish = Some(queue);
                             ui.close();
                         }
                     });
 
-                    if row.response().clicked() {
-                        app.window.queue.1 = index;
-                        let _ = app.track_add(*queue, context);
+                    // drag-and-drop, for re-ordering. only in queue order.
+                    if app.setting.sort_queue.is_none() {
+                        response.dnd_set_drag_payload(index);
+
+                        if let Some(from) = response.dnd_hover_payload::<usize>() {
+                            let stroke = response.ctx.style().visuals.selection.stroke;
+                            let painter = response.ctx.layer_painter(response.layer_id);
+                            // an entry moved down will land below the target entry.
+                            let y = if *from < index { response.rect.bottom() } else { response.rect.top() };
+                            painter.hline(response.rect.x_range(), y, stroke);
+                        }
+
+                        if let Some(from) = response.dnd_release_payload::<usize>() {
+                            shift = Some((*from, index));
+                        }
+                    }
+
+                    if response.clicked() {
+                        if modifier.command {
+                            // toggle entry selection.
+                            if let Some(i) = app.queue.pick.iter().position(|x| *x == index) {
+                                app.queue.pick.remove(i);
+                            } else {
+                                app.queue.pick.push(index);
+                            }
+                        } else if modifier.shift {
+                            // select every entry from the last selected entry, in display order.
+                            let last = app.queue.pick.last().copied().unwrap_or(app.queue.index);
+                            let last = order.iter().position(|x| *x == last).unwrap_or(position);
+
+                            for x in &order[last.min(position)..=last.max(position)] {
+                                if !app.queue.pick.contains(x) {
+                                    app.queue.pick.push(*x);
+                                }
+                            }
+                        } else {
+                            app.queue.pick.clear();
+                            app.queue.jump(index);
+                            let _ = app.track_add(queue, context);
+                        }
                     }
                 })
             });
 
+            if let Some(finish) = finish {
+                App::error_result(app.track_finish(finish, context));
+            }
+
+            if let Some((from, to)) = shift && from != to {
+                app.queue.shift(from, to);
+            }
+
+            if let Some((list, target)) = playlist {
+                let list: Vec<_> = list.iter().map(|x| app.queue.list[*x]).collect();
+                let list = Self::get_entry_key(app, &list);
+                app.playlist.insert(target, list);
+            }
+
             if let Some(detach) = detach {
-                if detach.1 {
-                    App::error_result(app.track_skip_b(context));
+                App::error_result(Queue::detach(app, &detach, context));
+            }
+        });
+    }
+
+    //================================================================
+    // folder layout.
+    //================================================================
+
+    fn draw_folder(app: &mut App, context: &egui::Context) {
+        Self::draw_panel_layout(app, context);
+        Self::draw_panel_status(app, context);
+
+        if app.library.list_folder.is_empty() {
+            app.library.list_folder = app.library.make_folder();
+        }
+
+        egui::CentralPanel::default().show(context, |ui| {
+            let mut action = None;
+
+            egui::ScrollArea::vertical()
+                .auto_shrink(false)
+                .show(ui, |ui| {
+                    for folder in &app.library.list_folder {
+                        Self::draw_folder_node(app, ui, folder, &mut action);
+                    }
+                });
+
+            match action {
+                Some(FolderAction::Play(list, index)) => {
+                    App::error_result(Queue::play(app, list, index, context));
+                }
+                Some(FolderAction::Queue(list, next)) => {
+                    App::error_result(Queue::add(app, list, next, context));
+                }
+                Some(FolderAction::Playlist(list, target)) => {
+                    let list = Self::get_entry_key(app, &list);
+                    app.playlist.insert(target, list);
+                }
+                None => {}
+            }
+        });
+    }
+
+    /// Draw a folder, with every sub-folder and track in it. Every action will play or queue the folder recursively, in path order.
+    fn draw_folder_node(
+        app: &App,
+        ui: &mut egui::Ui,
+        folder: &Folder,
+        action: &mut Option<FolderAction>,
+    ) {
+        let response = egui::CollapsingHeader::new(&folder.name)
+            .show(ui, |ui| {
+                for child in &folder.list_folder {
+                    Self::draw_folder_node(app, ui, child, action);
+                }
+
+                for entry in &folder.list_track {
+                    let track = app.get_state(*entry).2;
+                    let name = Path::new(&track.path)
+                        .file_name()
+                        .map(|x| x.to_string_lossy().to_string())
+                        .unwrap_or_default();
+
+                    // every CUE sheet track shares the same file.
+                    let name = match track.offset {
+                        Some(_) => format!("{name} - {}", track.name),
+                        None => name,
+                    };
+
+                    let response = ui
+                        .add(
+                            egui::Label::new(name)
+                                .selectable(false)
+                                .sense(egui::Sense::click()),
+                        )
+                        .on_hover_text(&track.path);
+
+                    response.context_menu(|ui| {
+                        if let Some(next) = Self::draw_queue_menu(ui) {
+                            *action = Some(FolderAction::Queue(vec![*entry], next));
+                        }
+
+                        if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                            *action = Some(FolderAction::Playlist(vec![*entry], target));
+                        }
+                    });
+
+                    // play the folder from the track onward.
+                    if response.double_clicked() {
+                        let list = folder.get_track(&app.library);
+                        let index = list.iter().position(|x| x == entry).unwrap_or_default();
+                        *action = Some(FolderAction::Play(list, index));
+                    }
+                }
+            })
+            .header_response;
+
+        response.context_menu(|ui| {
+            if ui.button("Play").clicked() {
+                *action = Some(FolderAction::Play(folder.get_track(&app.library), 0));
+                ui.close();
+            }
+
+            if let Some(next) = Self::draw_queue_menu(ui) {
+                *action = Some(FolderAction::Queue(folder.get_track(&app.library), next));
+            }
+
+            if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                *action = Some(FolderAction::Playlist(
+                    folder.get_track(&app.library),
+                    target,
+                ));
+            }
+        });
+    }
+
+    //================================================================
+    // playlist layout.
+    //================================================================
+
+    #[rustfmt::skip]
+    fn draw_playlist(app: &mut App, context: &egui::Context) {
+        Self::draw_panel_layout(app, context);
+        Self::draw_panel_status(app, context);
+
+        let rect = context.available_rect();
+
+        egui::SidePanel::left("panel_playlist")
+            .resizable(false)
+            .exact_width(rect.max.x / 3.0)
+            .show(context, |ui| {
+                let mut click = None;
+                let mut queue = None;
+                let mut remove = None;
+
+                ui.add_space(6.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("New Playlist").clicked() {
+                        app.window.playlist = Some(app.playlist.create());
+                    }
+                    if ui.button("New Smart Playlist").on_hover_text("Create a playlist from every track matching a rule.").clicked() {
+                        app.window.playlist = Some(app.playlist.create_rule(Some(Rule::default())));
+                    }
+                });
+
+                ui.separator();
+
+                let table = TableBuilder::new(ui)
+                    .striped(true)
+                    .sense(egui::Sense::click())
+                    .column(Column::remainder())
+                    .header(16.0, |mut header| {
+                        header.col(|ui| { ui.strong(format!("Playlist ({})", app.playlist.list_entry.len())); });
+                    });
+
+                table.body(|ui| {
+                    ui.rows(16.0, app.playlist.list_entry.len(), |mut row| {
+                        let index = row.index();
+                        let entry = app.playlist.list_entry.get(index).unwrap();
+
+                        row.set_selected(app.window.playlist == Some(index));
+
+                        row.col(|ui| { ui.add(egui::Label::new(&entry.name).selectable(false)); });
+
+                        row.response().context_menu(|ui| {
+                            if let Some(next) = Self::draw_queue_menu(ui) {
+                                queue = Some((index, next));
+                            }
+
+                            ui.separator();
+
+                            if ui.button("Delete playlist").clicked() {
+                                remove = Some(index);
+                                ui.close();
+                            }
+                        });
+
+                        if row.response().clicked() {
+                            app.window.playlist = Some(index);
+                        }
+
+                        if row.response().double_clicked() {
+                            click = Some(index);
+                        }
+                    });
+                });
+
+                if let Some(click) = click {
+                    let list = app.playlist.list_entry[click].get_entry(&app.library, &app.statistic);
+                    App::error_result(Queue::play(app, list, 0, context));
+                }
+
+                if let Some((index, next)) = queue {
+                    let list = app.playlist.list_entry[index].get_entry(&app.library, &app.statistic);
+                    App::error_result(Queue::add(app, list, next, context));
                 }
 
-                // TO-DO handle queue management in a better way...
-                app.window.queue.0.remove(detach.0);
+                if let Some(remove) = remove {
+                    app.playlist.list_entry.remove(remove);
+
+                    app.window.playlist = match app.window.playlist {
+                        Some(select) if select == remove => None,
+                        Some(select) if select > remove => Some(select - 1),
+                        select => select,
+                    };
+                }
+            });
+
+        egui::CentralPanel::default().show(context, |ui| {
+            let Some(select) = app.window.playlist else {
+                return;
+            };
+
+            let mut click = None;
+            let mut shift = None;
+            let mut remove = None;
+
+            ui.horizontal(|ui| {
+                let entry = app.playlist.list_entry.get_mut(select).unwrap();
+                let smart = entry.rule.is_some();
+
+                ui.add(egui::TextEdit::singleline(&mut entry.name).hint_text("Playlist name"));
+
+                if ui.button("Play").clicked() {
+                    click = Some(0);
+                }
+
+                if !smart && ui.button("Add Queue").on_hover_text("Add every track in the queue to the playlist.").clicked() {
+                    let list = Self::get_entry_key(app, &app.queue.list);
+                    app.playlist.insert(select, list);
+                }
+            });
+
+            ui.separator();
+
+            if app.playlist.list_entry[select].rule.is_some() {
+                click = Self::draw_playlist_rule(app, ui, select).or(click);
             }
+
+            let entry = app.playlist.list_entry.get(select).unwrap();
+
+            if entry.rule.is_some() {
+                if let Some(click) = click {
+                    let list = app.playlist.list_entry[select].get_entry(&app.library, &app.statistic);
+                    App::error_result(Queue::play(app, list, click, context));
+                }
+
+                return;
+            }
+
+            let table = TableBuilder::new(ui)
+                .striped(true)
+                .sense(egui::Sense::click_and_drag())
+                .column(Column::auto().resizable(true))
+                .column(Column::remainder().resizable(true).clip(true))
+                .column(Column::remainder().resizable(true).clip(true))
+                .column(Column::remainder().resizable(true).clip(true))
+                .column(Column::remainder().resizable(true).clip(true))
+                .header(16.0, |mut header| {
+                    header.col(|ui| { ui.strong("Number"); });
+                    header.col(|ui| { ui.strong("Group");  });
+                    header.col(|ui| { ui.strong("Album");  });
+                    header.col(|ui| { ui.strong("Track");  });
+                    header.col(|ui| { ui.strong("Time");   });
+                });
+
+            table.body(|ui| {
+                ui.rows(16.0, entry.list_track.len(), |mut row| {
+                    let index = row.index();
+                    let key = entry.list_track.get(index).unwrap();
+
+                    row.col(|ui| { ui.add(egui::Label::new((index + 1).to_string()).selectable(false)); });
+
+                    if let Some(state) = app.library.get_entry(key) {
+                        let (group, album, track) = app.get_state(state);
+
+                        row.col(|ui| { ui.add(egui::Label::new(&group.name).selectable(false));                            });
+                        row.col(|ui| { ui.add(egui::Label::new(&album.name).selectable(false));                            });
+                        row.col(|ui| { ui.add(egui::Label::new(&track.name).selectable(false));                            });
+                        row.col(|ui| { ui.add(egui::Label::new(Self::format_time(track.time.as_secs() as usize)).selectable(false)); });
+                    } else {
+                        // track is no longer in the library.
+                        row.col(|_| {});
+                        row.col(|_| {});
+                        row.col(|ui| { ui.add(egui::Label::new(egui::RichText::new(key).weak()).selectable(false)).on_hover_text("Track not found in the library."); });
+                        row.col(|_| {});
+                    }
+
+                    let response = row.response();
+
+                    response.context_menu(|ui| {
+                        if ui.button("Remove from playlist").clicked() {
+                            remove = Some(index);
+                            ui.close();
+                        }
+                    });
+
+                    // drag-and-drop, for re-ordering.
+                    response.dnd_set_drag_payload(index);
+
+                    if response.dnd_hover_payload::<usize>().is_some() {
+                        let stroke = response.ctx.style().visuals.selection.stroke;
+                        let painter = response.ctx.layer_painter(response.layer_id);
+                        painter.hline(response.rect.x_range(), response.rect.top(), stroke);
+                    }
+
+                    if let Some(from) = response.dnd_release_payload::<usize>() {
+                        shift = Some((*from, index));
+                    }
+
+                    if response.double_clicked() {
+                        // play from this track, skipping every track no longer in the library.
+                        let count = entry.list_track[..index].iter().filter(|x| app.library.get_entry(x).is_some()).count();
+                        click = Some(count);
+                    }
+                })
+            });
+
+            if let Some(click) = click {
+                let list = app.playlist.list_entry[select].get_entry(&app.library, &app.statistic);
+                App::error_result(Queue::play(app, list, click, context));
+            }
+
+            if let Some((from, to)) = shift && from != to {
+                app.playlist.list_entry[select].shift(from, to);
+            }
+
+            if let Some(remove) = remove {
+                app.playlist.list_entry[select].list_track.remove(remove);
+            }
+        });
+    }
+
+    /// Draw the rule editor and the match list of a smart playlist. Returns the match index to play from, if any.
+    #[rustfmt::skip]
+    fn draw_playlist_rule(app: &mut App, ui: &mut egui::Ui, select: usize) -> Option<usize> {
+        let mut click = None;
+        let mut change = false;
+        let mut remove = None;
+
+        let entry = app.playlist.list_entry.get_mut(select).unwrap();
+        let rule = entry.rule.as_mut().unwrap();
+
+        ui.horizontal(|ui| {
+            change |= ui.radio_value(&mut rule.any, false, "Match every rule").changed();
+            change |= ui.radio_value(&mut rule.any, true,  "Match any rule").changed();
+        });
+
+        for (i, condition) in rule.list_condition.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt(("condition", i))
+                    .selected_text(condition.get_name())
+                    .show_ui(ui, |ui| {
+                        for kind in Condition::get_list() {
+                            let name = kind.get_name();
+
+                            if ui.selectable_label(name == condition.get_name(), name).clicked() && name != condition.get_name() {
+                                *condition = kind;
+                                change = true;
+                            }
+                        }
+                    });
+
+                match condition {
+                    Condition::Genre(text) | Condition::Path(text) => {
+                        change |= ui.text_edit_singleline(text).changed();
+                    }
+                    Condition::Year(a, b) => {
+                        change |= ui.add(egui::DragValue::new(a)).changed();
+                        ui.label("and");
+                        change |= ui.add(egui::DragValue::new(b)).changed();
+                    }
+                    Condition::Time(minute)     => { change |= ui.add(egui::DragValue::new(minute).suffix(" minutes")).changed(); }
+                    Condition::PlayCount(count) => { change |= ui.add(egui::DragValue::new(count).suffix(" times")).changed();  }
+                    Condition::PlayTime(day)    => { change |= ui.add(egui::DragValue::new(day).suffix(" days")).changed();     }
+                }
+
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+
+        if ui.button("Add Rule").clicked() {
+            rule.list_condition.push(Condition::Genre(String::default()));
+            change = true;
+        }
+
+        if let Some(remove) = remove {
+            rule.list_condition.remove(remove);
+            change = true;
+        }
+
+        if change {
+            entry.list_match = None;
+        }
+
+        ui.separator();
+
+        let list = entry.get_entry(&app.library, &app.statistic);
+
+        let table = TableBuilder::new(ui)
+            .striped(true)
+            .sense(egui::Sense::click())
+            .column(Column::auto().resizable(true))
+            .column(Column::remainder().resizable(true).clip(true))
+            .column(Column::remainder().resizable(true).clip(true))
+            .column(Column::remainder().resizable(true).clip(true))
+            .column(Column::remainder().resizable(true).clip(true))
+            .header(16.0, |mut header| {
+                header.col(|ui| { ui.strong("Number"); });
+                header.col(|ui| { ui.strong("Group");  });
+                header.col(|ui| { ui.strong("Album");  });
+                header.col(|ui| { ui.strong("Track");  });
+                header.col(|ui| { ui.strong("Time");   });
+            });
+
+        table.body(|ui| {
+            ui.rows(16.0, list.len(), |mut row| {
+                let index = row.index();
+                let (group, album, track) = app.get_state(list[index]);
+
+                row.col(|ui| { ui.add(egui::Label::new((index + 1).to_string()).selectable(false));                });
+                row.col(|ui| { ui.add(egui::Label::new(&group.name).selectable(false));                            });
+                row.col(|ui| { ui.add(egui::Label::new(&album.name).selectable(false));                            });
+                row.col(|ui| { ui.add(egui::Label::new(&track.name).selectable(false));                            });
+                row.col(|ui| { ui.add(egui::Label::new(Self::format_time(track.time.as_secs() as usize)).selectable(false)); });
+
+                if row.response().double_clicked() {
+                    click = Some(index);
+                }
+            })
         });
+
+        click
     }
 
     //================================================================
@@ -465,8 +1129,7 @@ impl Window {
 
                 if ui.button("Select Library Folder").clicked() {
                     if let Some(folder) = rfd::FileDialog::new().pick_folder() {
-                        app.library = Library::scan(&folder.as_path().display().to_string());
-                        app.window.layout = Layout::Library;
+                        Self::library_scan(app, folder);
                     }
                 }
             });
@@ -484,13 +1147,50 @@ impl Window {
         egui::CentralPanel::default().show(context, |ui| {
             ui.collapsing("General", |ui| {
                 if ui.button("Scan Folder").clicked() && let Some(folder) = rfd::FileDialog::new().pick_folder() {
-                    app.library = Library::scan(&folder.as_path().display().to_string());
-                    app.window.layout = Layout::Library;
+                    Self::library_scan(app, folder);
                 }
 
                 ui.checkbox(&mut app.setting.window_media, "Allow multi-media key usage").on_hover_text("Will take effect on restart.");
                 ui.checkbox(&mut app.setting.window_tray,  "Show tray icon").on_hover_text("Will take effect on restart.");
                 ui.checkbox(&mut app.setting.window_push,  "Show track notification").on_hover_text("Will take effect on restart.");
+
+                ui.add(egui::Slider::new(&mut app.setting.bookmark_time, 0..=120).text("Resume tracks longer than (minutes)")).on_hover_text("Remember the play position of long tracks. Set to 0 to disable.");
+                ui.checkbox(&mut app.setting.bookmark_kind, "Resume audiobook and podcast tracks").on_hover_text("Remember the play position of tracks with an audiobook, audio theatre, podcast or speech genre.");
+                ui.checkbox(&mut app.setting.chapter_skip, "Skip by chapter").on_hover_text("Skip to the previous or next chapter within a track, if it has any, with the skip buttons or media keys.");
+                ui.checkbox(&mut app.setting.playlist_relative, "Save playlist paths relative to the playlist file").on_hover_text("Allows moving a playlist file along with the music folder, or between machines.");
+            });
+
+            //================================================================
+
+            ui.collapsing("Remote", |ui| {
+                ui.checkbox(&mut app.setting.remote_allow, "Allow remote control").on_hover_text("Serve an HTTP and WebSocket API, to control Melodix from another device. Will take effect on restart.");
+
+                ui.add_enabled_ui(app.setting.remote_allow, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Address");
+                        ui.text_edit_singleline(&mut app.setting.remote_address).on_hover_text("Will take effect on restart.");
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Token");
+                        ui.add(egui::TextEdit::singleline(&mut app.setting.remote_token.as_str()));
+                        if ui.button("Copy").clicked() {
+                            context.copy_text(app.setting.remote_token.clone());
+                        }
+                        if ui.button("Generate").on_hover_text("Will take effect on restart.").clicked() {
+                            app.setting.remote_token = Setting::make_token();
+                        }
+                    });
+                });
+
+                ui.checkbox(&mut app.setting.mpd_allow, "Allow MPD client").on_hover_text("Serve the MPD protocol, to control Melodix from any MPD client. Will take effect on restart.");
+
+                ui.add_enabled_ui(app.setting.mpd_allow, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Address");
+                        ui.text_edit_singleline(&mut app.setting.mpd_address).on_hover_text("Will take effect on restart.");
+                    });
+                });
             });
 
             //================================================================
@@ -538,48 +1238,174 @@ impl Window {
                     }
                 }
 
-                ui.checkbox(&mut app.setting.script_allow, "Allow Lua module scripting").on_hover_text("Will take effect on restart.");
+                ui.checkbox(&mut app.setting.script_allow, "Allow Lua module scripting").on_hover_text("Will take effect on restart.");
+
+                ui.add_enabled_ui(app.setting.script_allow, |ui| {
+                    for script in &mut app.script.script_list {
+                        if let Some(setting) = &mut script.0.setting {
+                            ui.collapsing(&script.0.name, |ui| {
+                                ui.group(|ui| {
+                                    ui.label(format!("Info: {}", &script.0.info));
+                                    ui.label(format!("From: {}", &script.0.from));
+                                    ui.label(format!("Version: {}", &script.0.version));
+                                });
+
+                                let table: mlua::Table = script.1.get("setting").unwrap();
+
+                                for (key, value) in setting.iter() {
+                                    let table: mlua::Table = table.get(&**key).unwrap();
+                                    App::error_result(value.draw(&script.1, &table, ui, ()));
+                                }
+                            });
+                        }
+                    }
+                });
+            });
+        });
+    }
+
+    //================================================================
+    // library layout.
+    //================================================================
+
+    fn draw_library(app: &mut App, context: &egui::Context) {
+        Self::draw_panel_layout(app, context);
+        Self::draw_panel_status(app, context);
+        Self::draw_panel_search(app, context);
+
+        if app.window.find.0.trim().is_empty() {
+            Self::draw_panel_track(app, context);
+
+            if app.window.browse.0 == Browse::Group {
+                Self::draw_panel_group(app, context);
+                Self::draw_panel_album(app, context);
+            } else {
+                Self::draw_panel_node(app, context);
+                Self::draw_panel_node_album(app, context);
+            }
+        } else {
+            Self::draw_panel_found(app, context);
+        }
+    }
+
+    // draw the global search bar.
+    fn draw_panel_search(app: &mut App, context: &egui::Context) {
+        egui::TopBottomPanel::top("search").show(context, |ui| {
+            ui.add_space(4.0);
+
+            ui.horizontal(|ui| {
+                let mut change = false;
+
+                if ui.button("✖").on_hover_text("Clear search.").clicked() {
+                    app.window.find.0.clear();
+                    change = true;
+                }
+
+                let edit = egui::TextEdit::singleline(&mut app.window.find.0)
+                    .hint_text("Search every group, album, track, genre, date and path...")
+                    .desired_width(f32::INFINITY);
+
+                let edit = ui.add(edit).on_hover_text(
+                    "Filter by field with artist:, album:, title:, genre:, year: (>1997, 1990..1999), time: (<5m, >3:30) or path:.",
+                );
+
+                if edit.changed() || change {
+                    match Search::new(&app.window.find.0) {
+                        Ok(search) => {
+                            app.window.find.1 = search.run(&app.library);
+                            app.window.find.2 = None;
+                        }
+                        Err(error) => {
+                            app.window.find.1.clear();
+                            app.window.find.2 = Some(error.to_string());
+                        }
+                    }
+                }
+            });
+
+            if let Some(error) = &app.window.find.2 {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+
+            ui.add_space(4.0);
+        });
+    }
+
+    // draw every track matching the global search, as a flat table.
+    #[rustfmt::skip]
+    fn draw_panel_found(app: &mut App, context: &egui::Context) {
+        egui::CentralPanel::default().show(context, |ui| {
+            let mut click = None;
+            let mut queue = None;
+            let mut playlist = None;
+
+            let table = TableBuilder::new(ui)
+                .striped(true)
+                .sense(egui::Sense::click())
+                .column(Column::remainder().resizable(true).clip(true))
+                .column(Column::remainder().resizable(true).clip(true))
+                .column(Column::remainder().resizable(true).clip(true))
+                .column(Column::remainder().resizable(true).clip(true))
+                .column(Column::auto().resizable(true))
+                .column(Column::auto().resizable(true))
+                .header(16.0, |mut header| {
+                    header.col(|ui| { ui.strong(format!("Track ({})", app.window.find.1.len())); });
+                    header.col(|ui| { ui.strong("Group"); });
+                    header.col(|ui| { ui.strong("Album"); });
+                    header.col(|ui| { ui.strong("Genre"); });
+                    header.col(|ui| { ui.strong("Date");  });
+                    header.col(|ui| { ui.strong("Time");  });
+                });
+
+            table.body(|ui| {
+                ui.rows(16.0, app.window.find.1.len(), |mut row| {
+                    let index = row.index();
+                    let entry = app.window.find.1[index];
+                    let (group, album, track) = app.get_state(entry);
 
-                ui.add_enabled_ui(app.setting.script_allow, |ui| {
-                    for script in &mut app.script.script_list {
-                        if let Some(setting) = &mut script.0.setting {
-                            ui.collapsing(&script.0.name, |ui| {
-                                ui.group(|ui| {
-                                    ui.label(format!("Info: {}", &script.0.info));
-                                    ui.label(format!("From: {}", &script.0.from));
-                                    ui.label(format!("Version: {}", &script.0.version));
-                                });
+                    row.set_selected(app.queue.state == Some(entry));
 
-                                let table: mlua::Table = script.1.get("setting").unwrap();
+                    row.col(|ui| { ui.add(egui::Label::new(&track.name).selectable(false));                                      });
+                    row.col(|ui| { ui.add(egui::Label::new(&group.name).selectable(false));                                      });
+                    row.col(|ui| { ui.add(egui::Label::new(&album.name).selectable(false));                                      });
+                    row.col(|ui| { ui.add(egui::Label::new(track.kind.as_deref().unwrap_or_default()).selectable(false));       });
+                    row.col(|ui| { ui.add(egui::Label::new(track.date.as_deref().unwrap_or_default()).selectable(false));       });
+                    row.col(|ui| { ui.add(egui::Label::new(Self::format_time(track.time.as_secs() as usize)).selectable(false)); });
 
-                                for (key, value) in setting.iter() {
-                                    let table: mlua::Table = table.get(&**key).unwrap();
-                                    App::error_result(value.draw(&script.1, &table, ui, ()));
-                                }
-                            });
+                    row.response().context_menu(|ui| {
+                        if let Some(next) = Self::draw_queue_menu(ui) {
+                            queue = Some((entry, next));
                         }
+
+                        if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                            playlist = Some((track.get_key(), target));
+                        }
+                    });
+
+                    if row.response().double_clicked() {
+                        click = Some(index);
                     }
                 });
             });
-        });
-    }
 
-    //================================================================
-    // library layout.
-    //================================================================
+            if let Some(click) = click {
+                let list = app.window.find.1.clone();
+                App::error_result(Queue::play(app, list, click, context));
+            }
 
-    fn draw_library(app: &mut App, context: &egui::Context) {
-        Self::draw_panel_layout(app, context);
-        Self::draw_panel_status(app, context);
+            if let Some((entry, next)) = queue {
+                App::error_result(Queue::add(app, vec![entry], next, context));
+            }
 
-        Self::draw_panel_track(app, context);
-        Self::draw_panel_group(app, context);
-        Self::draw_panel_album(app, context);
+            if let Some((key, target)) = playlist {
+                app.playlist.insert(target, vec![key]);
+            }
+        });
     }
 
     // draw the top track status bar. hidden if no track is available.
     fn draw_panel_status(app: &mut App, context: &egui::Context) {
-        if app.window.state.is_some() {
+        if app.queue.state.is_some() {
             egui::TopBottomPanel::top("status").show(context, |ui| {
                 egui::ScrollArea::horizontal().show(ui, |ui| {
                     ui.add_space(6.0);
@@ -593,7 +1419,7 @@ impl Window {
                         )
                         .clicked()
                         {
-                            App::error_result(app.track_skip_a(context));
+                            App::error_result(app.chapter_skip_a(context));
                         }
 
                         let image = if app.system.sink.is_paused() {
@@ -616,29 +1442,61 @@ impl Window {
                         )
                         .clicked()
                         {
-                            App::error_result(app.track_skip_b(context));
+                            App::error_result(app.chapter_skip_b(context));
                         }
 
+                        let hover = match app.queue.repeat {
+                            Repeat::Off => "Repeat: off",
+                            Repeat::One => "Repeat: track",
+                            Repeat::All => "Repeat: queue",
+                        };
+
                         if Self::draw_button_image(
                             ui,
                             Self::IMAGE_REPEAT,
-                            app.window.repeat,
+                            app.queue.repeat != Repeat::Off,
                             app.setting.window_theme,
                         )
+                        .on_hover_text(hover)
                         .clicked()
                         {
-                            app.window.repeat = !app.window.repeat;
+                            app.queue.repeat = app.queue.repeat.next();
                         }
 
+                        let response = ui
+                            .add(egui::Button::new("⏹").selected(app.queue.stop != Stop::Off))
+                            .on_hover_text("Stop after...");
+
+                        Popup::menu(&response).show(|ui| {
+                            ui.selectable_value(&mut app.queue.stop, Stop::Off, "Do not stop");
+                            ui.selectable_value(
+                                &mut app.queue.stop,
+                                Stop::Track,
+                                "Stop after current track",
+                            );
+                            ui.selectable_value(
+                                &mut app.queue.stop,
+                                Stop::Album,
+                                "Stop after current album",
+                            );
+                        });
+
+                        let hover = match app.queue.random {
+                            Random::Off => "Random: off",
+                            Random::Track => "Random: track",
+                            Random::Album => "Random: album",
+                        };
+
                         if Self::draw_button_image(
                             ui,
                             Self::IMAGE_RANDOM,
-                            app.window.random,
+                            app.queue.random != Random::Off,
                             app.setting.window_theme,
                         )
+                        .on_hover_text(hover)
                         .clicked()
                         {
-                            app.window.random = !app.window.random;
+                            app.queue.set_random(app.queue.random.next());
                         }
 
                         let image = match app.system.sink.volume() {
@@ -682,17 +1540,56 @@ impl Window {
 
                         let mut seek = app.system.sink.get_pos().as_secs();
 
-                        if ui
-                            .add(
-                                Slider::new(&mut seek, 0..=track.time.as_secs())
-                                    .trailing_fill(true)
-                                    .show_value(false),
-                            )
-                            .changed()
-                        {
+                        let response = ui.add(
+                            Slider::new(&mut seek, 0..=track.time.as_secs())
+                                .trailing_fill(true)
+                                .show_value(false),
+                        );
+
+                        if response.changed() {
                             app.track_seek(seek as i64, false);
                         }
 
+                        // draw a marker for every chapter on the seek bar.
+                        if !track.time.is_zero() {
+                            let rect = response.rect;
+                            // the slider rail is inset by the handle radius on both sides.
+                            let edge = rect.height() / 2.5;
+                            let size = rect.width() - edge * 2.0;
+                            let line = ui.visuals().widgets.inactive.fg_stroke;
+
+                            for chapter in track.chapter.iter().skip(1) {
+                                let x = rect.left()
+                                    + edge
+                                    + size
+                                        * (chapter.time.as_secs_f32() / track.time.as_secs_f32());
+
+                                ui.painter().vline(x, rect.y_range(), line);
+                            }
+                        }
+
+                        // draw the chapter list.
+                        if let Some(index) = track.get_chapter(app.system.sink.get_pos()) {
+                            let response = ui.button(&track.chapter[index].name);
+
+                            Popup::menu(&response).show(|ui| {
+                                for (i, chapter) in track.chapter.iter().enumerate() {
+                                    let time = Self::format_time(chapter.time.as_secs() as usize);
+
+                                    if ui
+                                        .selectable_label(
+                                            i == index,
+                                            format!("{time} {}", chapter.name),
+                                        )
+                                        .clicked()
+                                    {
+                                        app.track_seek(chapter.time.as_secs() as i64, false);
+                                        ui.close();
+                                    }
+                                }
+                            });
+                        }
+
                         //================================================================
 
                         ui.separator();
@@ -748,9 +1645,13 @@ impl Window {
             .show(context, |ui| {
                 let mut sort = false;
                 let mut click = None;
+                let mut queue = None;
+                let mut playlist = None;
 
                 ui.add_space(6.0);
 
+                Self::draw_browse(app, ui);
+
                 if ui.text_edit_singleline(&mut app.window.search.0).changed() {
                     app.library.list_shown.0.clear();
                     app.library.list_shown.1.clear();
@@ -759,8 +1660,8 @@ impl Window {
                     app.window.select.1 = (None, None);
                     app.window.select.2 = (None, None);
 
-                    for (i, group) in app.library.list_group.iter().enumerate() {
-                        if group
+                    for i in app.library.get_group_order() {
+                        if app.library.list_group[i]
                             .name
                             .to_lowercase()
                             .trim()
@@ -800,6 +1701,16 @@ impl Window {
 
                         row.col(|ui| { ui.add(egui::Label::new(&group.name).selectable(false)); });
 
+                        row.response().context_menu(|ui| {
+                            if let Some(next) = Self::draw_queue_menu(ui) {
+                                queue = Some((*index, next));
+                            }
+
+                            if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                                playlist = Some((*index, target));
+                            }
+                        });
+
                         if row.response().clicked() {
                             app.window.select.0 = (Some(*index), Some(i));
                             app.window.select.1 = (None, None);
@@ -822,6 +1733,273 @@ impl Window {
                 if let Some(click) = click {
                     App::error_result(Self::queue_play_group(app, click.0, context));
                 }
+
+                if let Some((i_group, next)) = queue {
+                    let list = Self::get_group_entry(app, i_group);
+                    App::error_result(Queue::add(app, list, next, context));
+                }
+
+                if let Some((i_group, target)) = playlist {
+                    let list = Self::get_entry_key(app, &Self::get_group_entry(app, i_group));
+                    app.playlist.insert(target, list);
+                }
+            });
+    }
+
+    /// Draw the browse mode selector. Will re-build the browse node list on change.
+    fn draw_browse(app: &mut App, ui: &mut egui::Ui) {
+        let browse = app.window.browse.0;
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut app.window.browse.0, Browse::Group, "Group");
+            ui.selectable_value(&mut app.window.browse.0, Browse::Genre, "Genre");
+            ui.selectable_value(&mut app.window.browse.0, Browse::Year, "Year");
+            ui.selectable_value(&mut app.window.browse.0, Browse::Folder, "Folder");
+        });
+
+        if app.window.browse.0 != browse {
+            app.library.list_node = match app.window.browse.0 {
+                Browse::Group => Vec::default(),
+                mode => app.library.make_node(mode),
+            };
+
+            let shown = match app.window.browse.0 {
+                Browse::Group => app.library.get_group_order(),
+                _ => (0..app.library.list_node.len()).collect(),
+            };
+
+            app.library.list_shown = (shown, Vec::default(), Vec::default());
+            app.window.browse.1 = None;
+            app.window.search.0.clear();
+            app.window.select = ((None, None), (None, None), (None, None));
+        }
+
+        ui.add_space(4.0);
+    }
+
+    // draw the left library panel, for every browse mode other than group.
+    #[rustfmt::skip]
+    fn draw_panel_node(app: &mut App, context: &egui::Context) {
+        let rect = context.available_rect();
+
+        egui::SidePanel::left("panel_group")
+            .resizable(false)
+            .exact_width(rect.max.x / 2.0)
+            .show(context, |ui| {
+                let mut sort = false;
+                let mut click = None;
+                let mut queue = None;
+                let mut playlist = None;
+
+                ui.add_space(6.0);
+
+                Self::draw_browse(app, ui);
+
+                if ui.text_edit_singleline(&mut app.window.search.0).changed() {
+                    app.library.list_shown = (Vec::default(), Vec::default(), Vec::default());
+                    app.window.browse.1 = None;
+                    app.window.select = ((None, None), (None, None), (None, None));
+
+                    for (i, node) in app.library.list_node.iter().enumerate() {
+                        if node.name.to_lowercase().trim().contains(app.window.search.0.to_lowercase().trim()) {
+                            app.library.list_shown.0.push(i);
+                        }
+                    }
+                };
+
+                ui.separator();
+
+                let name = match app.window.browse.0 {
+                    Browse::Genre => "Genre",
+                    Browse::Year  => "Year",
+                    _             => "Folder",
+                };
+
+                let table = TableBuilder::new(ui)
+                    .striped(true)
+                    .sense(egui::Sense::click())
+                    .column(Column::remainder().clip(true))
+                    .header(16.0, |mut header| {
+                        header.col(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.strong(format!("{name} ({})", app.library.list_shown.0.len()));
+                                // a reversed folder hierarchy would list every sub-folder before its folder.
+                                if app.window.browse.0 != Browse::Folder && ui.button("⬆/⬇").clicked() {
+                                    sort = true;
+                                }
+                            });
+                        });
+                    });
+
+                table.body(|ui| {
+                    ui.rows(16.0, app.library.list_shown.0.len(), |mut row| {
+                        let index = *app.library.list_shown.0.get(row.index()).unwrap();
+                        let node = app.library.list_node.get(index).unwrap();
+
+                        row.set_selected(app.window.browse.1 == Some(index));
+
+                        row.col(|ui| {
+                            ui.add_space(node.depth as f32 * 12.0);
+                            ui.add(egui::Label::new(format!("{} ({})", node.name, node.list_track.len())).selectable(false));
+                        });
+
+                        row.response().context_menu(|ui| {
+                            if let Some(next) = Self::draw_queue_menu(ui) {
+                                queue = Some((index, next));
+                            }
+
+                            if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                                playlist = Some((index, target));
+                            }
+                        });
+
+                        if row.response().clicked() {
+                            app.window.browse.1 = Some(index);
+                            app.window.select = ((None, None), (None, None), (None, None));
+                            app.library.list_shown.1 = (0..node.list_album.len()).collect();
+                            app.library.list_shown.2.clear();
+                        }
+
+                        if row.response().double_clicked() {
+                            click = Some(index);
+                        }
+                    });
+                });
+
+                if sort {
+                    app.library.list_shown.0.reverse();
+                }
+
+                if let Some(click) = click {
+                    let list = app.library.list_node[click].list_track.clone();
+                    App::error_result(Queue::play(app, list, 0, context));
+                }
+
+                if let Some((index, next)) = queue {
+                    let list = app.library.list_node[index].list_track.clone();
+                    App::error_result(Queue::add(app, list, next, context));
+                }
+
+                if let Some((index, target)) = playlist {
+                    let list = Self::get_entry_key(app, &app.library.list_node[index].list_track);
+                    app.playlist.insert(target, list);
+                }
+            });
+    }
+
+    // draw the right library panel, for every album in the selected browse node.
+    #[rustfmt::skip]
+    fn draw_panel_node_album(app: &mut App, context: &egui::Context) {
+        let rect = context.available_rect();
+
+        egui::SidePanel::right("panel_album")
+            .resizable(false)
+            .exact_width(rect.max.x / 2.0)
+            .show(context, |ui| {
+                let Some(select) = app.window.browse.1 else {
+                    return;
+                };
+
+                let mut sort = false;
+                let mut click = None;
+                let mut queue = None;
+                let mut playlist = None;
+
+                ui.add_space(6.0);
+
+                let node = app.library.list_node.get(select).unwrap();
+
+                // every track of an album that is in the node.
+                let get_track = |i_group: usize, i_album: usize| -> Vec<(usize, usize, usize)> {
+                    node.list_track.iter().copied().filter(|x| (x.0, x.1) == (i_group, i_album)).collect()
+                };
+
+                if ui.text_edit_singleline(&mut app.window.search.1).changed() {
+                    app.library.list_shown.1.clear();
+                    app.library.list_shown.2.clear();
+                    app.window.select = ((None, None), (None, None), (None, None));
+
+                    for (i, (i_group, i_album)) in node.list_album.iter().enumerate() {
+                        let album = &app.library.list_group[*i_group].list_album[*i_album];
+
+                        if album.name.to_lowercase().trim().contains(app.window.search.1.to_lowercase().trim()) {
+                            app.library.list_shown.1.push(i);
+                        }
+                    }
+                };
+
+                ui.separator();
+
+                let table = TableBuilder::new(ui)
+                    .striped(true)
+                    .sense(egui::Sense::click())
+                    .column(Column::remainder().clip(true))
+                    .header(16.0, |mut header| {
+                        header.col(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.strong(format!("Album ({})", app.library.list_shown.1.len()));
+                                if ui.button("⬆/⬇").clicked() {
+                                    sort = true;
+                                }
+                            });
+                        });
+                    });
+
+                table.body(|ui| {
+                    ui.rows(16.0, app.library.list_shown.1.len(), |mut row| {
+                        let (i_group, i_album) = node.list_album[app.library.list_shown.1[row.index()]];
+                        let group = &app.library.list_group[i_group];
+                        let album = &group.list_album[i_album];
+
+                        row.set_selected((app.window.select.0.0, app.window.select.1.0) == (Some(i_group), Some(i_album)));
+
+                        // show the album year when browsing by year.
+                        let name = match album.list_track.iter().find_map(|x| x.get_year()) {
+                            Some(year) if app.window.browse.0 == Browse::Year => format!("{year} - {} - {}", album.name, group.name),
+                            _ => format!("{} - {}", album.name, group.name),
+                        };
+
+                        row.col(|ui| { ui.add(egui::Label::new(name).selectable(false)); });
+
+                        row.response().context_menu(|ui| {
+                            if let Some(next) = Self::draw_queue_menu(ui) {
+                                queue = Some((get_track(i_group, i_album), next));
+                            }
+
+                            if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                                playlist = Some((get_track(i_group, i_album), target));
+                            }
+                        });
+
+                        if row.response().clicked() {
+                            app.window.select.0 = (Some(i_group), None);
+                            app.window.select.1 = (Some(i_album), None);
+                            app.window.select.2 = (None, None);
+                            app.library.list_shown.2 = get_track(i_group, i_album).iter().map(|x| x.2).collect();
+                        }
+
+                        if row.response().double_clicked() {
+                            click = Some(get_track(i_group, i_album));
+                        }
+                    });
+                });
+
+                if sort {
+                    app.library.list_shown.1.reverse();
+                }
+
+                if let Some(click) = click {
+                    App::error_result(Queue::play(app, click, 0, context));
+                }
+
+                if let Some((list, next)) = queue {
+                    App::error_result(Queue::add(app, list, next, context));
+                }
+
+                if let Some((list, target)) = playlist {
+                    let list = Self::get_entry_key(app, &list);
+                    app.playlist.insert(target, list);
+                }
             });
     }
 
@@ -835,6 +2013,8 @@ impl Window {
                 if let Some(select) = app.window.select.0.0 {
                     let mut sort = false;
                     let mut click = None;
+                    let mut queue = None;
+                    let mut playlist = None;
 
                     ui.add_space(6.0);
 
@@ -892,6 +2072,16 @@ impl Window {
                                 ui.add(egui::Label::new(&album.name).selectable(false));
                             });
 
+                            row.response().context_menu(|ui| {
+                                if let Some(next) = Self::draw_queue_menu(ui) {
+                                    queue = Some((select, *index, next));
+                                }
+
+                                if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                                    playlist = Some((select, *index, target));
+                                }
+                            });
+
                             if row.response().clicked() {
                                 app.window.select.1 = (Some(*index), Some(i));
                                 app.window.select.2 = (None, None);
@@ -914,6 +2104,17 @@ impl Window {
                     if let Some(click) = click {
                         App::error_result(Self::queue_play_album(app, click.0, click.1, context));
                     }
+
+                    if let Some((i_group, i_album, next)) = queue {
+                        let list = Self::get_album_entry(app, i_group, i_album);
+                        App::error_result(Queue::add(app, list, next, context));
+                    }
+
+                    if let Some((i_group, i_album, target)) = playlist {
+                        let list =
+                            Self::get_entry_key(app, &Self::get_album_entry(app, i_group, i_album));
+                        app.playlist.insert(target, list);
+                    }
                 }
             });
     }
@@ -927,6 +2128,9 @@ impl Window {
             .exact_height(rect.max.y / 2.0)
             .show(context, |ui| {
                 let mut click = None;
+                let mut finish = None;
+                let mut queue = None;
+                let mut playlist = None;
 
                 if let Some(i_group) = app.window.select.0.0
                     && let Some(i_album) = app.window.select.1.0
@@ -965,23 +2169,39 @@ impl Window {
                     if app.setting.window_time  { table = table.column(Column::remainder().resizable(true).clip(true)); }
 
                     let table = table.header(16.0, |mut header| {
-                        if app.setting.window_track { header.col(|ui| { ui.strong("Track"); }); }
-                                                      header.col(|ui| { ui.strong("Title"); });
-                        if app.setting.window_kind  { header.col(|ui| { ui.strong("Genre"); }); }
-                        if app.setting.window_date  { header.col(|ui| { ui.strong("Date");  }); }
-                        if app.setting.window_time  { header.col(|ui| { ui.strong("Time");  }); }
+                        let sort = &mut app.setting.sort_track;
+
+                        if app.setting.window_track { header.col(|ui| { Self::draw_sort_header(ui, "Track", Sort::Number, sort); }); }
+                                                      header.col(|ui| { Self::draw_sort_header(ui, "Title", Sort::Title,  sort); });
+                        if app.setting.window_kind  { header.col(|ui| { Self::draw_sort_header(ui, "Genre", Sort::Genre,  sort); }); }
+                        if app.setting.window_date  { header.col(|ui| { Self::draw_sort_header(ui, "Date",  Sort::Date,   sort); }); }
+                        if app.setting.window_time  { header.col(|ui| { Self::draw_sort_header(ui, "Time",  Sort::Time,   sort); }); }
                     });
 
+                    // sort every shown track, or keep the album order.
+                    let mut shown = std::mem::take(&mut app.library.list_shown.2);
+
+                    match app.setting.sort_track {
+                        Some((sort, descend)) => shown.sort_by(|a, b| {
+                            let order = app.library.compare((i_group, i_album, *a), (i_group, i_album, *b), sort);
+                            if descend { order.reverse() } else { order }
+                        }),
+                        None => shown.sort(),
+                    }
+
+                    app.library.list_shown.2 = shown;
+
                     table.body(|ui| {
                         ui.rows(16.0, app.library.list_shown.2.len(), |mut row| {
                             let i = row.index();
-                            if let Some(select) = app.window.select.2.1 {
-                                row.set_selected(i == select);
-                            }
-
                             let index = app.library.list_shown.2.get(i).unwrap();
                             let track = album.list_track.get(*index).unwrap();
 
+                            // highlight by track index, as the row will move on sort.
+                            if let Some(select) = app.window.select.2.0 {
+                                row.set_selected(*index == select);
+                            }
+
                             if app.setting.window_track {
                                 row.col(|ui| {
                                     let order = track.track.unwrap_or_default().to_string();
@@ -1022,29 +2242,66 @@ impl Window {
                                 });
                             }
 
+                            row.response().context_menu(|ui| {
+                                if let Some(next) = Self::draw_queue_menu(ui) {
+                                    queue = Some(((i_group, i_album, *index), next));
+                                }
+
+                                if let Some(target) = Self::draw_playlist_menu(ui, &app.playlist) {
+                                    playlist = Some((track.get_key(), target));
+                                }
+
+                                if app.bookmark.list_mark.contains_key(&track.get_key()) && ui.button("Mark as finished").clicked() {
+                                    finish = Some((i_group, i_album, *index));
+                                    ui.close();
+                                }
+                            });
+
                             if row.response().clicked() {
                                 app.window.select.2 = (Some(*index), Some(i));
-                                click = Some(*index);
+                                click = Some(i);
                             }
                         });
                     });
 
+                    if let Some(finish) = finish {
+                        App::error_result(app.track_finish(finish, context));
+                    }
+
                     if let Some(click) = click {
+                        // queue the rest of the table in display order if sorted, or else the rest of the album.
+                        let list = match app.setting.sort_track {
+                            Some(_) => app.library.list_shown.2[click..].to_vec(),
+                            None => {
+                                let track = app.library.list_shown.2[click];
+                                let count = app.library.list_group[i_group].list_album[i_album].list_track.len();
+                                (track..count).collect()
+                            }
+                        };
+
                         App::error_result(Self::queue_play_track(
                             app,
                             app.window.select.0.0.unwrap(),
                             app.window.select.1.0.unwrap(),
-                            click,
+                            list,
                             context,
                         ));
                     }
+
+                    if let Some((entry, next)) = queue {
+                        App::error_result(Queue::add(app, vec![entry], next, context));
+                    }
+
+                    if let Some((key, target)) = playlist {
+                        app.playlist.insert(target, vec![key]);
+                    }
                 }
             });
     }
 
     //================================================================
 
-    fn format_time(time: usize) -> String {
+    pub fn format_time(time: usize) -> String {
         let time_a = time / 60;
         let time_b = time % 60;
 